@@ -19,3 +19,8 @@ pub mod model;
 ///
 /// Not exposed
 pub(crate) mod io;
+
+/// Text utilities (edit distance).
+///
+/// Not exposed
+pub(crate) mod text;
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+/// Casing style of a word part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Style {
+	/// "sur"
+	Lower,
+	/// "Saint"
+	Title,
+	/// "MAROLLES"
+	Upper,
+}
+
+impl Style {
+	/// Every style, in the order of the vote arrays.
+	const ALL: [Style; 3] = [Style::Lower, Style::Title, Style::Upper];
+
+	/// Returns the style of a word part, or `None` if it has no cased letter
+	/// or mixes styles (e.g. "McDonald").
+	///
+	/// A single uppercase letter is title case.
+	fn of(part: &str) -> Option<Style> {
+		let mut cased = part.chars().filter(|c| c.is_lowercase() || c.is_uppercase());
+		let first = cased.next()?;
+		let rest: Vec<char> = cased.collect();
+		if first.is_lowercase() {
+			rest.iter().all(|c| c.is_lowercase()).then_some(Style::Lower)
+		} else if rest.is_empty() || rest.iter().all(|c| c.is_lowercase()) {
+			Some(Style::Title)
+		} else {
+			rest.iter().all(|c| c.is_uppercase()).then_some(Style::Upper)
+		}
+	}
+
+	/// Rewrites a word part in this style.
+	pub(crate) fn apply(self, part: &str) -> String {
+		match self {
+			Style::Lower => part.to_lowercase(),
+			Style::Upper => part.to_uppercase(),
+			Style::Title => {
+				let mut chars = part.chars();
+				match chars.next() {
+					Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+					None => String::new(),
+				}
+			}
+		}
+	}
+}
+
+/// Rewrites each part of a word, separated by whitespace or hyphens, in the
+/// style returned by `style_of` (parts without a style are kept as they are).
+///
+/// `style_of` receives each part and whether it is the first one.
+pub(crate) fn restyle(word: &str, style_of: impl Fn(&str, bool) -> Option<Style>) -> String {
+	let mut restyled = String::with_capacity(word.len());
+	let mut part = String::new();
+	for c in word.chars().chain(std::iter::once(' ')) {
+		if c.is_whitespace() || c == '-' {
+			match style_of(&part, restyled.is_empty()) {
+				Some(style) => restyled.push_str(&style.apply(&part)),
+				None => restyled.push_str(&part),
+			}
+			restyled.push(c);
+			part.clear();
+		} else {
+			part.push(c);
+		}
+	}
+	// Drop the sentinel separator
+	restyled.pop();
+	restyled
+}
+
+/// Casing styles observed in a corpus, per word part and overall.
+///
+/// Used by `Casing::Learned` to write generated words the way the corpus
+/// does: a part seen in the corpus takes its majority style (e.g. "sur" in
+/// "Saint-Denis-sur-Mer"), any other part the majority style of the corpus.
+/// First parts are counted apart, since they are often capitalized
+/// ("Le-Mans" but "Saint-Jean-le-Blanc").
+///
+/// # Invariants
+/// - `parts` keys are lowercase, paired with whether the part comes first
+/// - Vote arrays follow the order of `Style::ALL`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LearnedCasing {
+	parts: HashMap<(bool, String), [usize; 3]>,
+	overall: [usize; 3],
+}
+
+impl LearnedCasing {
+	/// Counts the styles of every word part of `sentences`.
+	pub(crate) fn learn<'a>(sentences: impl Iterator<Item = &'a String>) -> Self {
+		let mut casing = Self::default();
+		for sentence in sentences {
+			casing.add(sentence);
+		}
+		casing
+	}
+
+	/// Counts the styles of the word parts of one more sentence.
+	pub(crate) fn add(&mut self, sentence: &str) {
+		let parts = sentence.split(|c: char| c.is_whitespace() || c == '-').filter(|part| !part.is_empty());
+		for (position, part) in parts.enumerate() {
+			let Some(style) = Style::of(part) else { continue };
+			let index = Style::ALL.iter().position(|s| *s == style).unwrap_or(0);
+			self.parts.entry((position == 0, part.to_lowercase())).or_insert([0; 3])[index] += 1;
+			self.overall[index] += 1;
+		}
+	}
+
+	/// Returns the fraction of each style for a word part (any casing) at
+	/// the same position (first or not), or `None` if it was never seen there.
+	pub(crate) fn part_votes(&self, part: &str, first: bool) -> Option<[f64; 3]> {
+		self.parts.get(&(first, part.to_lowercase())).and_then(Self::fractions)
+	}
+
+	/// Returns the fraction of each style over the whole corpus,
+	/// or `None` if the corpus has no cased word part.
+	pub(crate) fn overall_votes(&self) -> Option<[f64; 3]> {
+		Self::fractions(&self.overall)
+	}
+
+	/// Returns the style with the most votes (ties favor lowercase, then title case).
+	pub(crate) fn majority(votes: [f64; 3]) -> Style {
+		let mut best = 0;
+		for (index, vote) in votes.iter().enumerate().skip(1) {
+			if *vote > votes[best] {
+				best = index;
+			}
+		}
+		Style::ALL[best]
+	}
+
+	/// Normalizes vote counts into fractions.
+	fn fractions(counts: &[usize; 3]) -> Option<[f64; 3]> {
+		let total: usize = counts.iter().sum();
+		if total == 0 {
+			return None;
+		}
+		Some(counts.map(|count| count as f64 / total as f64))
+	}
+}
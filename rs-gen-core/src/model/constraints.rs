@@ -0,0 +1,239 @@
+use super::distribution::Distribution;
+use super::multigram_model::END_CHAR;
+use regex::{Regex, RegexBuilder};
+
+/// Word length used as a hard cap when constraints are active and no
+/// maximum length is set, so that a constrained search always terminates.
+pub(crate) const CONSTRAINED_MAX_LEN: usize = 64;
+
+/// Weight multiplier applied to the character that extends a partial match
+/// of a required substring (`ends_with`, `contains`).
+const STEERING_BOOST: f64 = 8.0;
+
+/// Constraints a generated word must satisfy.
+///
+/// Constraints are honored during sampling rather than by blind rejection:
+/// - `excludes`: a character that would complete an excluded substring is never drawn
+/// - `ends_with`, `contains`, `regex`: the end of the word cannot be drawn
+///   until they are satisfied
+/// - `ends_with`, `contains`: the character extending a partial match is favored
+///
+/// Since a regular expression cannot be checked on a partial word, `regex` is
+/// only verified on complete words. Words that reach a dead end or the length
+/// cap without satisfying every constraint are regenerated, up to
+/// `max_attempts` times, after which generation fails with an explicit error.
+///
+/// All comparisons are case-insensitive.
+///
+/// # Invariants
+/// - Stored substrings are lowercase and non-empty
+#[derive(Clone, Debug)]
+pub struct Constraints {
+	/// Required suffix.
+	ends_with: Option<String>,
+
+	/// Substrings that must all appear in the word.
+	contains: Vec<String>,
+
+	/// Substrings that must not appear in the word.
+	excludes: Vec<String>,
+
+	/// Pattern the complete word must match.
+	regex: Option<Regex>,
+
+	/// Maximum number of generation attempts before reporting failure.
+	pub max_attempts: usize,
+}
+
+impl Default for Constraints {
+	fn default() -> Self {
+		Self {
+			ends_with: None,
+			contains: Vec::new(),
+			excludes: Vec::new(),
+			regex: None,
+			max_attempts: 100,
+		}
+	}
+}
+
+impl Constraints {
+	/// Returns `true` if at least one constraint is set.
+	pub fn is_active(&self) -> bool {
+		self.ends_with.is_some() || !self.contains.is_empty() || !self.excludes.is_empty() || self.regex.is_some()
+	}
+
+	/// Returns the required suffix, if any.
+	pub fn ends_with(&self) -> Option<&str> {
+		self.ends_with.as_deref()
+	}
+
+	/// Sets the required suffix. `None` removes it.
+	///
+	/// # Errors
+	/// Returns an error if the suffix is empty.
+	pub fn set_ends_with(&mut self, suffix: Option<&str>) -> Result<(), String> {
+		self.ends_with = match suffix {
+			Some(s) => Some(Self::normalize(s, "Suffix")?),
+			None => None,
+		};
+		Ok(())
+	}
+
+	/// Adds a substring that must appear in the word.
+	///
+	/// # Errors
+	/// Returns an error if the substring is empty.
+	pub fn add_contains(&mut self, substring: &str) -> Result<(), String> {
+		self.contains.push(Self::normalize(substring, "Required substring")?);
+		Ok(())
+	}
+
+	/// Adds a substring that must not appear in the word.
+	///
+	/// # Errors
+	/// Returns an error if the substring is empty.
+	pub fn add_excludes(&mut self, substring: &str) -> Result<(), String> {
+		self.excludes.push(Self::normalize(substring, "Excluded substring")?);
+		Ok(())
+	}
+
+	/// Sets the pattern the complete word must match (case-insensitive). `None` removes it.
+	///
+	/// # Errors
+	/// Returns an error if the pattern is not a valid regular expression.
+	pub fn set_regex(&mut self, pattern: Option<&str>) -> Result<(), String> {
+		self.regex = match pattern {
+			Some(p) => Some(
+				RegexBuilder::new(p)
+					.case_insensitive(true)
+					.build()
+					.map_err(|e| format!("Invalid regex '{}': {}", p, e))?,
+			),
+			None => None,
+		};
+		Ok(())
+	}
+
+	/// Removes every constraint.
+	pub fn clear(&mut self) {
+		*self = Self { max_attempts: self.max_attempts, ..Self::default() };
+	}
+
+	/// Lowercases a constraint substring and rejects empty values.
+	fn normalize(value: &str, what: &str) -> Result<String, String> {
+		if value.is_empty() {
+			return Err(format!("{} cannot be empty", what));
+		}
+		Ok(value.to_lowercase())
+	}
+
+	/// Checks that the constraints are not contradictory and only use
+	/// characters that the models can produce.
+	///
+	/// # Errors
+	/// Returns a description of the first constraint that cannot be met.
+	pub(crate) fn check_feasible<F: Fn(char) -> bool>(&self, knows_char: F) -> Result<(), String> {
+		let required = self.ends_with.iter().chain(self.contains.iter());
+		for substring in required {
+			if let Some(excluded) = self.excludes.iter().find(|excluded| substring.contains(excluded.as_str())) {
+				return Err(format!(
+					"Constraint cannot be met: required '{}' contains excluded '{}'",
+					substring, excluded
+				));
+			}
+			if let Some(c) = substring.chars().find(|c| !knows_char(*c)) {
+				return Err(format!(
+					"Constraint cannot be met: character '{}' of '{}' never appears in the selected models",
+					c, substring
+				));
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns `true` if a complete word satisfies every constraint.
+	pub(crate) fn is_satisfied(&self, word: &str) -> bool {
+		let word = word.to_lowercase();
+		self.ends_with.as_ref().is_none_or(|suffix| word.ends_with(suffix.as_str()))
+			&& self.contains.iter().all(|substring| word.contains(substring.as_str()))
+			&& !self.excludes.iter().any(|substring| word.contains(substring.as_str()))
+			&& self.regex.as_ref().is_none_or(|regex| regex.is_match(&word))
+	}
+
+	/// Describes the constraints, for error messages.
+	pub(crate) fn describe(&self) -> String {
+		let mut parts = Vec::new();
+		if let Some(suffix) = &self.ends_with {
+			parts.push(format!("ends with '{}'", suffix));
+		}
+		for substring in &self.contains {
+			parts.push(format!("contains '{}'", substring));
+		}
+		for substring in &self.excludes {
+			parts.push(format!("excludes '{}'", substring));
+		}
+		if let Some(regex) = &self.regex {
+			parts.push(format!("matches '{}'", regex.as_str()));
+		}
+		parts.join(", ")
+	}
+
+	/// Filters and reweights the candidates following `word`.
+	///
+	/// - Removes characters completing an excluded substring.
+	/// - Removes the end of the word while the word does not satisfy the constraints.
+	/// - Boosts the character extending a partial match of a required substring.
+	///   Matches of length 0 are only extended once `ready` is `true`.
+	///
+	/// Candidates are compared case-insensitively (case-sensitive models
+	/// have uppercase transitions).
+	pub(crate) fn apply(&self, distribution: &mut Distribution, word: &str, ready: bool) {
+		let word = word.to_lowercase();
+
+		if !self.excludes.is_empty() {
+			distribution.retain(|c| c == END_CHAR || !self.completes_excluded(&word, c));
+		}
+
+		if !self.is_satisfied(&word) {
+			distribution.retain(|c| c != END_CHAR);
+		}
+
+		let missing_contains = self.contains.iter().filter(|substring| !word.contains(substring.as_str()));
+		let unfinished_suffix = self.ends_with.iter().filter(|suffix| !word.ends_with(suffix.as_str()));
+		for target in unfinished_suffix.chain(missing_contains) {
+			let matched = Self::partial_match(&word, target);
+			if (matched > 0 || ready)
+				&& let Some(next_char) = target[matched..].chars().next()
+			{
+				distribution.scale(next_char, STEERING_BOOST);
+				let mut upper = next_char.to_uppercase();
+				if let (Some(upper), None) = (upper.next(), upper.next())
+					&& upper != next_char
+				{
+					distribution.scale(upper, STEERING_BOOST);
+				}
+			}
+		}
+	}
+
+	/// Returns `true` if appending `c` to `word` completes an excluded substring.
+	fn completes_excluded(&self, word: &str, c: char) -> bool {
+		let c = c.to_lowercase().next().unwrap_or(c);
+		self.excludes.iter().any(|excluded| {
+			excluded.ends_with(c) && word.ends_with(&excluded[..excluded.len() - c.len_utf8()])
+		})
+	}
+
+	/// Returns the byte length of the longest proper prefix of `target`
+	/// that is a suffix of `word`.
+	fn partial_match(word: &str, target: &str) -> usize {
+		target
+			.char_indices()
+			.map(|(index, _)| index)
+			.skip(1)
+			.filter(|index| word.ends_with(&target[..*index]))
+			.last()
+			.unwrap_or(0)
+	}
+}
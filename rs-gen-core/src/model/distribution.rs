@@ -0,0 +1,200 @@
+use super::prediction_input::Sampling;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Discrete distribution over next characters.
+///
+/// A `Distribution` is a list of `(char, weight)` pairs, kept in a stable
+/// order, that can be reshaped (temperature, top-k, top-p) and sampled.
+/// Weights do not need to be normalized.
+///
+/// # Invariants
+/// - Every weight is finite and strictly positive
+/// - Each character appears at most once
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Distribution {
+	entries: Vec<(char, f64)>,
+}
+
+impl Distribution {
+	/// Builds a distribution from transition counts.
+	///
+	/// Transitions observed fewer than `min_count` times are dropped.
+	pub(crate) fn from_counts<'a, I>(counts: I, min_count: usize) -> Self
+	where
+		I: IntoIterator<Item = (&'a char, &'a usize)>,
+	{
+		let entries = counts
+			.into_iter()
+			.filter(|(_, occurrence)| **occurrence > 0 && **occurrence >= min_count)
+			.map(|(next_char, occurrence)| (*next_char, *occurrence as f64))
+			.collect();
+		Self { entries }
+	}
+
+	/// Builds a distribution from arbitrary weights.
+	///
+	/// Non-positive and non-finite weights are dropped.
+	pub(crate) fn from_weights<I>(weights: I) -> Self
+	where
+		I: IntoIterator<Item = (char, f64)>,
+	{
+		let entries = weights
+			.into_iter()
+			.filter(|(_, weight)| weight.is_finite() && *weight > 0.0)
+			.collect();
+		Self { entries }
+	}
+
+	/// Builds the weighted mixture of several distributions.
+	///
+	/// Each component is normalized to a probability distribution and scaled
+	/// by its weight before summing. Empty components and non-positive weights
+	/// are ignored, so the remaining weights are implicitly renormalized.
+	pub(crate) fn mixture<I>(components: I) -> Self
+	where
+		I: IntoIterator<Item = (Distribution, f64)>,
+	{
+		let mut mixed: BTreeMap<char, f64> = BTreeMap::new();
+		for (component, weight) in components {
+			let total: f64 = component.entries.iter().map(|(_, w)| w).sum();
+			if total <= 0.0 || weight <= 0.0 {
+				continue;
+			}
+			for (next_char, w) in component.entries {
+				*mixed.entry(next_char).or_insert(0.0) += weight * w / total;
+			}
+		}
+		Self::from_weights(mixed)
+	}
+
+	/// Returns `true` if no character can be drawn.
+	pub(crate) fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Returns the normalized probability of each character, in a stable order.
+	pub(crate) fn probabilities(&self) -> impl Iterator<Item = (char, f64)> + '_ {
+		let total: f64 = self.entries.iter().map(|(_, weight)| weight).sum();
+		self.entries.iter().map(move |(next_char, weight)| (*next_char, weight / total))
+	}
+
+	/// Keeps only the characters for which `keep` returns `true`.
+	pub(crate) fn retain<F: FnMut(char) -> bool>(&mut self, mut keep: F) {
+		self.entries.retain(|(next_char, _)| keep(*next_char));
+	}
+
+	/// Multiplies the weight of `next_char` by `factor`, if present.
+	///
+	/// A non-positive factor removes the character.
+	pub(crate) fn scale(&mut self, next_char: char, factor: f64) {
+		for (c, weight) in &mut self.entries {
+			if *c == next_char {
+				*weight *= factor;
+			}
+		}
+		self.entries.retain(|(_, weight)| weight.is_finite() && *weight > 0.0);
+	}
+
+	/// Applies the temperature, top-k and top-p settings of `sampling`, in that order.
+	pub(crate) fn shape(&mut self, sampling: &Sampling) {
+		if sampling.temperature() != 1.0 {
+			self.apply_temperature(sampling.temperature());
+		}
+		if let Some(top_k) = sampling.top_k() {
+			self.keep_top_k(top_k);
+		}
+		if let Some(top_p) = sampling.top_p() {
+			self.keep_top_p(top_p);
+		}
+	}
+
+	/// Raises every weight to `1 / temperature`.
+	///
+	/// Computed in log space and shifted by the maximum, so very small
+	/// temperatures do not overflow.
+	fn apply_temperature(&mut self, temperature: f32) {
+		let inverse = 1.0 / temperature as f64;
+		let max_log = self
+			.entries
+			.iter()
+			.map(|(_, weight)| weight.ln() * inverse)
+			.fold(f64::NEG_INFINITY, f64::max);
+
+		for (_, weight) in &mut self.entries {
+			*weight = (weight.ln() * inverse - max_log).exp();
+		}
+		self.entries.retain(|(_, weight)| *weight > 0.0);
+	}
+
+	/// Returns the indices of the entries sorted by decreasing weight.
+	///
+	/// Ties keep character order, so truncation is deterministic.
+	fn ranking(&self) -> Vec<usize> {
+		let mut indices: Vec<usize> = (0..self.entries.len()).collect();
+		indices.sort_by(|a, b| self.entries[*b].1.total_cmp(&self.entries[*a].1));
+		indices
+	}
+
+	/// Keeps only the `k` most likely characters.
+	fn keep_top_k(&mut self, k: usize) {
+		if self.entries.len() <= k {
+			return;
+		}
+		let mut keep = vec![false; self.entries.len()];
+		for index in self.ranking().into_iter().take(k) {
+			keep[index] = true;
+		}
+		let mut index = 0;
+		self.entries.retain(|_| {
+			index += 1;
+			keep[index - 1]
+		});
+	}
+
+	/// Keeps the smallest set of most likely characters whose cumulative
+	/// probability reaches `p` (nucleus sampling).
+	fn keep_top_p(&mut self, p: f32) {
+		let total: f64 = self.entries.iter().map(|(_, weight)| weight).sum();
+		if total <= 0.0 {
+			return;
+		}
+
+		let threshold = p as f64 * total;
+		let mut keep = vec![false; self.entries.len()];
+		let mut cumulative = 0.0;
+		for index in self.ranking() {
+			keep[index] = true;
+			cumulative += self.entries[index].1;
+			if cumulative >= threshold {
+				break;
+			}
+		}
+		let mut index = 0;
+		self.entries.retain(|_| {
+			index += 1;
+			keep[index - 1]
+		});
+	}
+
+	/// Draws a character with probability proportional to its weight.
+	///
+	/// Returns `None` if the distribution is empty.
+	pub(crate) fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<char> {
+		let total: f64 = self.entries.iter().map(|(_, weight)| weight).sum();
+		if total <= 0.0 {
+			return None;
+		}
+
+		let mut r = rng.random_range(0.0..total);
+		for (next_char, weight) in &self.entries {
+			if r < *weight {
+				return Some(*next_char);
+			}
+			r -= weight;
+		}
+
+		// Floating point rounding: fall back to the last entry
+		self.entries.last().map(|(next_char, _)| *next_char)
+	}
+}
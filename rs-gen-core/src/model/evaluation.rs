@@ -0,0 +1,263 @@
+use super::generator::Generator;
+use super::multigram_model::MultiGramModel;
+use super::prediction_input::PredictionInput;
+use crate::io::{get_filename, read_file};
+use crate::text::edit_distance;
+use rand_chacha::ChaCha8Rng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::thread;
+
+/// Largest character n-gram size reported by the distinct-n ratios.
+const DISTINCT_MAX_N: usize = 4;
+
+/// Held-out evaluation of a generation configuration on a single corpus.
+///
+/// The lines of a `.dat` file are shuffled and split into a training part,
+/// used to build a `MultiGramModel`, and a held-out part, used to measure
+/// how well the model predicts words it has never seen.
+///
+/// # Responsibilities
+/// - Split a corpus reproducibly (same `split_seed`, same split)
+/// - Train a model on the training part only
+/// - Report held-out perplexity and statistics over a generated batch
+///   (see `EvaluationReport`), so that `PredictionInput` settings can be compared
+#[derive(Debug)]
+pub struct Evaluation {
+	/// Generator holding the model trained on the training part.
+	generator: Generator,
+
+	/// Name of the evaluated model (file name without extension).
+	name: String,
+
+	/// Training words, lowercased, sorted and without duplicates.
+	training: Vec<String>,
+
+	/// Held-out words, as written in the corpus.
+	held_out: Vec<String>,
+
+	/// Number of corpus lines per length (in characters).
+	corpus_lengths: BTreeMap<usize, usize>,
+}
+
+/// Metrics computed by `Evaluation::run`.
+#[derive(Clone, Debug)]
+pub struct EvaluationReport {
+	/// Number of distinct training words.
+	pub training_size: usize,
+
+	/// Number of held-out lines.
+	pub held_out_size: usize,
+
+	/// Per-character perplexity of the held-out words (the end of each word counts
+	/// as a character). Lower means the model generalizes better.
+	pub perplexity: f64,
+
+	/// Number of generated words.
+	pub sample_size: usize,
+
+	/// Fraction of generated words absent from the training part.
+	pub novelty_rate: f64,
+
+	/// Fraction of generated words found in the held-out part: new to the
+	/// model, yet plausible enough to be real.
+	pub held_out_rate: f64,
+
+	/// Number of generated words per length (in characters).
+	pub generated_lengths: BTreeMap<usize, usize>,
+
+	/// Number of corpus lines (both parts) per length (in characters).
+	pub corpus_lengths: BTreeMap<usize, usize>,
+
+	/// Distinct-n ratios over the generated batch: for each character n-gram
+	/// size `n` (1 to 4), distinct n-grams divided by total n-grams.
+	/// Higher means more diverse output.
+	pub distinct: BTreeMap<usize, f64>,
+
+	/// Average edit distance between each generated word and its nearest
+	/// training word (`0` for words copied from the training part).
+	pub mean_nearest_distance: f64,
+}
+
+impl Evaluation {
+	/// Splits a corpus and trains a model on the training part.
+	///
+	/// # Parameters
+	/// - `filepath`: `.dat` file, one word per line (empty lines are ignored)
+	/// - `train_ratio`: fraction of the lines used for training, in (0.0, 1.0)
+	/// - `split_seed`: seed of the shuffle deciding which lines are held out
+	///
+	/// # Errors
+	/// - Returns an error if the file cannot be read.
+	/// - Returns an error if `train_ratio` leaves either part empty.
+	///
+	/// # Notes
+	/// - No binary cache is read or written: the model is always rebuilt.
+	pub fn new<P: AsRef<Path>>(
+		filepath: P,
+		train_ratio: f32,
+		split_seed: u64,
+	) -> Result<Self, Box<dyn std::error::Error>> {
+		if !(train_ratio > 0.0 && train_ratio < 1.0) {
+			return Err(format!("Train ratio must be in (0.0, 1.0), got {}", train_ratio).into());
+		}
+
+		let mut lines: Vec<String> = read_file(&filepath)?.into_iter().filter(|line| !line.is_empty()).collect();
+		lines.shuffle(&mut ChaCha8Rng::seed_from_u64(split_seed));
+		let corpus_lengths = Self::lengths(lines.iter().map(String::as_str));
+
+		let training_size = (lines.len() as f32 * train_ratio).round() as usize;
+		if training_size == 0 || training_size == lines.len() {
+			return Err(format!(
+				"Train ratio {} leaves an empty part with {} lines",
+				train_ratio,
+				lines.len()
+			)
+			.into());
+		}
+		let held_out = lines.split_off(training_size);
+
+		let name = get_filename(&filepath)?;
+		let mut model = MultiGramModel::from_lines(&lines)?;
+		model.set_name(&name);
+		model.freeze();
+
+		let training: BTreeSet<String> = lines.iter().map(|line| line.to_lowercase()).collect();
+
+		Ok(Self {
+			generator: Generator::from_models(vec![model]),
+			name,
+			training: training.into_iter().collect(),
+			held_out,
+			corpus_lengths,
+		})
+	}
+
+	/// Creates a `PredictionInput` selecting the evaluated model.
+	pub fn make_prediction_input(&self) -> PredictionInput {
+		let mut prediction_input = self.generator.make_prediction_input();
+		// Cannot fail: the generator holds exactly this model
+		let _ = prediction_input.set_model_intensity(&self.name, 1.0);
+		prediction_input
+	}
+
+	/// Evaluates a configuration.
+	///
+	/// # Parameters
+	/// - `prediction_input`: settings used to score held-out words and to generate
+	///   (see `make_prediction_input`)
+	/// - `sample_size`: number of words to generate
+	///
+	/// # Behavior
+	/// - Held-out words are scored with the `max_n`, smoothing and `min_count`
+	///   of `prediction_input` (see `Generator::score`).
+	/// - Words are generated with a single random generator seeded from
+	///   `rng_seed`, so a seeded evaluation is reproducible.
+	///
+	/// # Errors
+	/// Returns an error if `sample_size` is zero or if generation fails.
+	pub fn run(&self, prediction_input: &PredictionInput, sample_size: usize) -> Result<EvaluationReport, String> {
+		if sample_size == 0 {
+			return Err("Sample size must be positive".to_owned());
+		}
+
+		let mut log_prob = 0.0;
+		let mut symbols = 0;
+		for word in &self.held_out {
+			for (_, score) in self.generator.score(word, prediction_input) {
+				log_prob += score.total;
+				symbols += score.len();
+			}
+		}
+		let perplexity = (-log_prob / symbols as f64).exp();
+
+		let mut rng = match prediction_input.rng_seed {
+			Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+			None => ChaCha8Rng::from_rng(&mut rand::rng()),
+		};
+		let generated = (0..sample_size)
+			.map(|_| self.generator.predict_with_rng(prediction_input, &mut rng).map(|word| word.to_lowercase()))
+			.collect::<Result<Vec<String>, String>>()?;
+
+		let held_out: BTreeSet<String> = self.held_out.iter().map(|word| word.to_lowercase()).collect();
+		let novel = generated.iter().filter(|word| self.training.binary_search(word).is_err()).count();
+		let in_held_out = generated.iter().filter(|word| held_out.contains(*word)).count();
+
+		let distances = self.nearest_distances(&generated);
+
+		Ok(EvaluationReport {
+			training_size: self.training.len(),
+			held_out_size: self.held_out.len(),
+			perplexity,
+			sample_size,
+			novelty_rate: novel as f64 / sample_size as f64,
+			held_out_rate: in_held_out as f64 / sample_size as f64,
+			generated_lengths: Self::lengths(generated.iter().map(String::as_str)),
+			corpus_lengths: self.corpus_lengths.clone(),
+			distinct: Self::distinct(&generated),
+			mean_nearest_distance: distances.iter().sum::<usize>() as f64 / sample_size as f64,
+		})
+	}
+
+	/// Counts words per length (in characters).
+	fn lengths<'a>(words: impl Iterator<Item = &'a str>) -> BTreeMap<usize, usize> {
+		let mut lengths = BTreeMap::new();
+		for word in words {
+			*lengths.entry(word.chars().count()).or_insert(0) += 1;
+		}
+		lengths
+	}
+
+	/// Computes the distinct-n ratios of a batch of words.
+	///
+	/// Sizes for which no word is long enough are omitted.
+	fn distinct(words: &[String]) -> BTreeMap<usize, f64> {
+		let words: Vec<Vec<char>> = words.iter().map(|word| word.chars().collect()).collect();
+		(1..=DISTINCT_MAX_N)
+			.filter_map(|n| {
+				let ngrams: Vec<&[char]> = words.iter().flat_map(|word| word.windows(n)).collect();
+				if ngrams.is_empty() {
+					return None;
+				}
+				let distinct: BTreeSet<&[char]> = ngrams.iter().copied().collect();
+				Some((n, distinct.len() as f64 / ngrams.len() as f64))
+			})
+			.collect()
+	}
+
+	/// Returns, for each word, the edit distance to its nearest training word.
+	///
+	/// Words are split among threads; each search keeps shrinking its distance
+	/// limit as closer training words are found.
+	fn nearest_distances(&self, words: &[String]) -> Vec<usize> {
+		let chunk_size = words.len().div_ceil(num_cpus::get()).max(1);
+		thread::scope(|scope| {
+			let handles: Vec<_> = words
+				.chunks(chunk_size)
+				.map(|chunk| {
+					scope.spawn(move || chunk.iter().map(|word| self.nearest_distance(word)).collect::<Vec<usize>>())
+				})
+				.collect();
+			handles.into_iter().flat_map(|handle| handle.join().expect("Distance thread panicked")).collect()
+		})
+	}
+
+	/// Returns the edit distance between `word` and its nearest training word.
+	fn nearest_distance(&self, word: &str) -> usize {
+		if self.training.binary_search_by(|training| training.as_str().cmp(word)).is_ok() {
+			return 0;
+		}
+		let mut best = usize::MAX;
+		for training in &self.training {
+			if let Some(distance) = edit_distance(word, training, best - 1) {
+				best = distance;
+				if best == 1 {
+					break;
+				}
+			}
+		}
+		best
+	}
+}
@@ -1,10 +1,50 @@
 use crate::io;
-use crate::model::multigram_model::{MultiGramModel, START_CHAR};
-use crate::model::prediction_input::{PredictionInput, StartSeed};
-use rand::Rng;
+use crate::model::casing::{self, LearnedCasing, Style};
+use crate::model::constraints::CONSTRAINED_MAX_LEN;
+use crate::model::distribution::Distribution;
+use crate::model::graphemes::Graphemes;
+use crate::model::multigram_model::{END_CHAR, MultiGramModel, START_CHAR};
+use crate::model::normalization::Normalization;
+use crate::model::novelty::{BkTree, TriePaths};
+use crate::model::prediction_input::{Blending, Casing, EndSeed, PredictionInput, Smoothing, StartSeed, Tokenization};
+use crate::model::score::WordScore;
+use crate::model::session::GenerationSession;
+use crate::model::tokens::{TokenModel, Vocabulary, tokenize};
+use rand_chacha::ChaCha8Rng;
+use rand::{Rng, SeedableRng};
 use std::cmp::{max, min};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Number of middle parts drawn by `infill` before picking the best joined one.
+const INFILL_CANDIDATES: usize = 16;
+
+/// Maximum number of candidates drawn in a round of `predict_batch`, so that
+/// a large `count` does not generate all its candidates at once. Independent
+/// of the number of threads, so that seeded batches are the same everywhere.
+const BATCH_ROUND: usize = 256;
+
+/// How `generate` builds a word.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+	/// Left to right, from the start seed.
+	Forward,
+	/// Right to left on the reversed models, from the end seed.
+	Backward,
+	/// Left to right, from the start seed, before a suffix of the given
+	/// number of characters that is appended afterwards.
+	Infill(usize),
+	/// Left to right over encoded word tokens (see `Tokenization::Words`).
+	Tokens,
+	/// Left to right over encoded grapheme clusters (see `Tokenization::Graphemes`).
+	Graphemes,
+}
+
+/// N-gram order used by `classify`. Short contexts generalize better to
+/// unseen words than whole prefixes, which mostly recognize training words.
+const CLASSIFY_ORDER: usize = 4;
 
 /// High-level generator managing multiple n-gram models.
 ///
@@ -14,7 +54,14 @@ use std::path::Path;
 /// - Generate sequences with randomness, seed control, and duplicate avoidance
 #[derive(Debug)]
 pub struct Generator {
-	models: HashMap<String, MultiGramModel>
+	models: BTreeMap<String, MultiGramModel>,
+	/// Token codes shared by the word-token models (see `train_tokens`).
+	vocabulary: Vocabulary,
+	/// Cluster codes shared by the grapheme models (see `train_graphemes`);
+	/// `None` until trained.
+	graphemes: Option<Graphemes>,
+	/// Normalization applied by every loaded model, and to custom seeds.
+	normalization: Normalization
 }
 
 impl Generator {
@@ -37,9 +84,34 @@ impl Generator {
 	/// - The directory path is normalized using `resolve_folder`.
 	/// - Only files directly contained in the directory are loaded
 	///   (subdirectories are ignored).
+	/// - Sentences are learned as written (see `with_normalization`).
 	pub fn new<P: AsRef<Path>>(filepath: P) -> Result<Self, Box<dyn std::error::Error>> {
+		Self::with_normalization(filepath, Normalization::default())
+	}
+
+	/// Creates a generator like `new`, normalizing the learned sentences.
+	///
+	/// # Parameters
+	/// - `filepath`: Path to a directory containing model files.
+	/// - `normalization`: applied to every learned sentence, to custom seeds
+	///   and to the duplicate check, so that equivalent spellings (e.g.
+	///   precomposed and decomposed accents) map to the same n-grams.
+	///
+	/// # Errors
+	/// Same as `new`.
+	///
+	/// # Notes
+	/// - Each normalization has its own binary caches
+	///   (see `MultiGramModel::with_normalization`).
+	pub fn with_normalization<P: AsRef<Path>>(
+		filepath: P,
+		normalization: Normalization,
+	) -> Result<Self, Box<dyn std::error::Error>> {
 		let mut generator = Self {
-			models: HashMap::new()
+			models: BTreeMap::new(),
+			vocabulary: Vocabulary::default(),
+			graphemes: None,
+			normalization
 		};
 
 		let string_path = match filepath.as_ref().to_str() {
@@ -61,6 +133,16 @@ impl Generator {
 		Ok(generator)
 	}
 
+	/// Creates a generator from already built models, keyed by their names.
+	pub(crate) fn from_models(models: Vec<MultiGramModel>) -> Self {
+		Self {
+			models: models.into_iter().map(|model| (model.get_name().to_owned(), model)).collect(),
+			vocabulary: Vocabulary::default(),
+			graphemes: None,
+			normalization: Normalization::default()
+		}
+	}
+
 	/// Returns the list of loaded model names.
 	///
 	/// Provides a read-only reference to internal names.
@@ -78,16 +160,21 @@ impl Generator {
 		if self.models.contains_key(&key) {
 			return Err(Box::from("Model already loaded".to_owned()));
 		}
-		let model = MultiGramModel::new(path)?;
+		let model = MultiGramModel::with_normalization(path, &self.normalization)?;
 		self.models.insert(key, model);
 		Ok(())
 	}
 
+	/// Returns the normalization applied to learned sentences and custom seeds.
+	pub fn normalization(&self) -> &Normalization {
+		&self.normalization
+	}
+
 	/// Creates a new `PredictionInput` with default intensity for all models.
 	///
 	/// Probabilities are automatically normalized.
-	pub fn make_prediction_input<'a>(&self) -> PredictionInput {
-		let mut models_probability: HashMap<String, f32> = HashMap::new();
+	pub fn make_prediction_input(&self) -> PredictionInput {
+		let mut models_probability: BTreeMap<String, f32> = BTreeMap::new();
 		for model in self.models.values() {
 			models_probability.insert(model.get_name().to_owned(), 0.0);
 		}
@@ -98,12 +185,11 @@ impl Generator {
 	///
 	/// Models with higher probability are more likely to appear earlier in the list.
 	/// Only models present in `self.models` with a positive weight are included.
-	pub fn get_random_models(
+	pub fn get_random_models<R: Rng + ?Sized>(
 		&self,
 		prediction_input: &PredictionInput,
+		rng: &mut R,
 	) -> Vec<String> {
-		let mut rng = rand::rng();
-
 		let mut scored: Vec<(String, f64)> = prediction_input
 			.models_probability()
 			.filter_map(|(name, weight): (&str, f32)| {
@@ -125,7 +211,7 @@ impl Generator {
 	/// Internal sequence generation logic using a single model selection.
 	///
 	/// # Parameters
-	/// - `prediction_input`: Controls randomness, start seed, and max_n.
+	/// - `prediction_input`: Controls randomness, start seed, end seed, and max_n.
 	///
 	/// # Returns
 	/// - `Ok(String)` with a generated sequence
@@ -133,110 +219,632 @@ impl Generator {
 	///
 	/// # Notes
 	/// - Handles `StartSeed::False`, `StartSeed::Random`, `StartSeed::Custom`.
-	/// - Iterates over characters safely (UTF-8 aware).
-	/// - Loops until no next character is returned.
-	fn internal_predict(&mut self, prediction_input: &PredictionInput) -> Result<String, String> {
+	/// - With an `EndSeed` only, generates right to left on the reversed models.
+	/// - With both seeds, generates the middle part between them (see `infill`).
+	/// - Every random draw comes from `rng`.
+	fn internal_predict<R: Rng + ?Sized>(
+		&self,
+		prediction_input: &PredictionInput,
+		rng: &mut R,
+	) -> Result<String, String> {
 		if self.models.is_empty() {
 			return Err("No models available for prediction".to_owned());
 		}
+		match prediction_input.tokenization {
+			Tokenization::Characters => {}
+			Tokenization::Words => return self.generate_tokens(prediction_input, rng),
+			Tokenization::Graphemes => return self.generate_graphemes(prediction_input, rng),
+		}
 
-		let max_n: usize = if prediction_input.max_n < 2 { 0 } else { prediction_input.max_n };
-
-		let mut word = String::new();
-		let mut global_prefix = String::new();
-
-		let mut models = self.get_random_models(prediction_input);
-		let mut model = match self.models.get_mut(&models[0]) {
-			Some(model) => model,
-			None => return Err("No model available for prediction".to_owned())
-		};
+		match (&prediction_input.start_seed, &prediction_input.end_seed) {
+			(_, EndSeed::False) => {
+				let prefix = self.start_seed(prediction_input, rng)?;
+				self.generate(prediction_input, prefix, Direction::Forward, rng)
+			}
+			(StartSeed::False, _) => {
+				let suffix: String = self.end_seed(prediction_input, rng)?.chars().rev().collect();
+				self.generate(prediction_input, suffix, Direction::Backward, rng)
+			}
+			_ => {
+				let prefix = self.start_seed(prediction_input, rng)?;
+				let suffix = self.end_seed(prediction_input, rng)?;
+				self.infill(prediction_input, &prefix, &suffix, rng)
+			}
+		}
+	}
 
+	/// Returns the beginning of the word set by the start seed (empty without one).
+	///
+	/// Custom seeds are normalized like the learned sentences (see `with_normalization`).
+	///
+	/// # Errors
+	/// Returns an error if a random seed is requested and none is available.
+	fn start_seed<R: Rng + ?Sized>(&self, prediction_input: &PredictionInput, rng: &mut R) -> Result<String, String> {
 		match &prediction_input.start_seed {
-			StartSeed::False => (),
+			StartSeed::False => Ok(String::new()),
+			StartSeed::Custom(s) => Ok(self.normalization.apply(s.strip_prefix(START_CHAR).unwrap_or(s))),
 			StartSeed::Random(random) => {
-				let n = if *random <= 0 {
-					rand::rng().random_range(2..=model.size() + 1)
-				} else {
-					max(2, min(*random, model.size() + 1))
-				};
-
-				word = match model.get_random_seed(n) {
-					Some(s) => s.to_owned(),
-					None => return Err("No seed available for prediction".to_owned())
-				};
-				if word.starts_with(START_CHAR) { word.remove(0); }
-				global_prefix = word.clone();
+				let models = self.get_random_models(prediction_input, rng);
+				Generator::random_seed(self.directed_model(&models[0], Direction::Forward)?, *random, rng)
 			}
-			StartSeed::Custom(s) => {
-				word = (*s).to_owned();
-				if word.starts_with(START_CHAR) { word.remove(0); }
-				global_prefix = word.clone();
+		}
+	}
+
+	/// Returns the ending of the word set by the end seed, in reading order
+	/// (empty without one). Custom seeds are normalized like the learned sentences.
+	///
+	/// # Errors
+	/// Returns an error if a random seed is requested and none is available,
+	/// or if the reversed models it is drawn from were not trained.
+	fn end_seed<R: Rng + ?Sized>(&self, prediction_input: &PredictionInput, rng: &mut R) -> Result<String, String> {
+		match &prediction_input.end_seed {
+			EndSeed::False => Ok(String::new()),
+			EndSeed::Custom(s) => Ok(self.normalization.apply(s.strip_suffix(END_CHAR).unwrap_or(s))),
+			EndSeed::Random(random) => {
+				let models = self.get_random_models(prediction_input, rng);
+				let seed = Generator::random_seed(self.directed_model(&models[0], Direction::Backward)?, *random, rng)?;
+				Ok(seed.chars().rev().collect())
 			}
 		}
+	}
+
+	/// Generates a word character by character from `seed`.
+	///
+	/// # Parameters
+	/// - `seed`: beginning of the word, in the generation `direction`
+	///   (reversed for `Direction::Backward`)
+	/// - `direction`: see `Direction`
+	///
+	/// # Returns
+	/// The word in reading order (the suffix of `Direction::Infill` is not included).
+	///
+	/// # Notes
+	/// - Iterates over characters safely (UTF-8 aware).
+	/// - Loops until no next character is returned.
+	/// - With `Novelty::during_generation` and `Direction::Forward`, the word is
+	///   followed in the tries of every loaded model, and the end of the word
+	///   is masked whenever the word is a training word.
+	fn generate<R: Rng + ?Sized>(
+		&self,
+		prediction_input: &PredictionInput,
+		seed: String,
+		direction: Direction,
+		rng: &mut R,
+	) -> Result<String, String> {
+		let max_n: usize = if prediction_input.max_n < 2 { 0 } else { prediction_input.max_n };
+		let mut models = self.get_random_models(prediction_input, rng);
+		let mut model = self.directed_model(&models[0], direction)?;
+
+		let mut word = seed;
+		let mut global_prefix = word.clone();
 
 		if !global_prefix.starts_with(START_CHAR) {
 			global_prefix.insert(0, START_CHAR);
 		}
 
+		let novelty = &prediction_input.novelty;
+		let mut paths = (direction == Direction::Forward && novelty.during_generation()).then(|| {
+			let tries = self.models.values().map(|model| model.novelty_trie(novelty.fold_accents()));
+			TriePaths::new(tries, novelty.fold_accents(), &word)
+		});
+
 		let models_size = models.len();
 		let mut model_index = 0;
-		let mut n = Generator::compute_n(global_prefix.chars().count(), max_n, prediction_input.randomness())?;
+		let mut n = Generator::compute_n(global_prefix.chars().count(), max_n, prediction_input.randomness(), rng)?;
+		n = self.adapt_n(model, &global_prefix, n, prediction_input, direction);
 		loop {
-			match model.predict(&mut global_prefix, n)? {
+			let duplicate = paths.as_ref().is_some_and(TriePaths::is_word);
+			let constrained = prediction_input.has_step_constraints() || duplicate;
+			let next_char = match (prediction_input.blending, prediction_input.smoothing) {
+				(Blending::PerCharacter, Smoothing::Backoff) if !constrained => {
+					model.predict(&global_prefix, n, prediction_input.sampling(), rng)?
+				}
+				(Blending::PerCharacter, Smoothing::WittenBell) if !constrained => {
+					model.predict_smoothed(&global_prefix, n, prediction_input.sampling(), rng)?
+				}
+				_ => self.predict_distribution(model, &global_prefix, &word, n, duplicate, prediction_input, direction, rng),
+			};
+			match next_char {
 				Some(c) => {
 					global_prefix.push(c);
-					if c != '>' {
+					if c != END_CHAR {
 						word.push(c);
+						if let Some(paths) = &mut paths {
+							paths.push(c);
+						}
 					} else {
 						break;
 					}
 				},
 				None => {
-					model_index = model_index + 1;
-					if model_index >= models_size {
+					model_index += 1;
+					// A mixture already covers every model: reduce n directly
+					if model_index >= models_size || prediction_input.blending == Blending::Mixture {
+						// Smallest n-gram already tried on every model: dead end
+						if n <= 2 {
+							break;
+						}
 						model_index = 0;
-						models = self.get_random_models(prediction_input);
-						model = match self.models.get_mut(&models[model_index]) {
-							Some(model) => model,
-							None => return Err("No model available for prediction".to_owned())
-						};
+						models = self.get_random_models(prediction_input, rng);
+						model = self.directed_model(&models[model_index], direction)?;
 						if prediction_input.reduce_random {
-							n = Generator::compute_randomness(prediction_input.randomness(), n - 1, n)?;
+							n = Generator::compute_randomness(prediction_input.randomness(), n - 1, n, rng)?;
 						} else {
-							n = n - 1;
+							n -= 1;
 						}
 						continue;
 					} else {
-						model = match self.models.get_mut(&models[model_index]) {
-							Some(model) => model,
-							None => return Err("No model available for prediction".to_owned())
-						};
+						model = self.directed_model(&models[model_index], direction)?;
 						continue;
 					}
 				}
 			};
 
-			n = Generator::compute_n(global_prefix.chars().count(), max_n, prediction_input.randomness())?;
+			n = Generator::compute_n(global_prefix.chars().count(), max_n, prediction_input.randomness(), rng)?;
 			model_index = 0;
-			models = self.get_random_models(prediction_input);
-			model = match self.models.get_mut(&models[model_index]) {
-				Some(model) => model,
-				None => return Err("No model available for prediction".to_owned())
-			};
+			models = self.get_random_models(prediction_input, rng);
+			model = self.directed_model(&models[model_index], direction)?;
+			n = self.adapt_n(model, &global_prefix, n, prediction_input, direction);
 		}
 
+		if direction == Direction::Backward {
+			word = word.chars().rev().collect();
+		}
 		Ok(word)
 	}
 
+	/// Generates a word token by token (see `Tokenization::Words`).
+	///
+	/// # Behavior
+	/// - A custom start seed is split into tokens and kept as written.
+	/// - Tokens are drawn from the token models like characters, then decoded;
+	///   each rare token is replaced by a rare token (or a new one, see
+	///   `PredictionInput::set_novel_tokens`) of a weighted model.
+	/// - Tokens are joined with single spaces.
+	///
+	/// # Errors
+	/// Returns an error if an end seed is set or if token models were not trained.
+	fn generate_tokens<R: Rng + ?Sized>(&self, prediction_input: &PredictionInput, rng: &mut R) -> Result<String, String> {
+		if prediction_input.end_seed != EndSeed::False {
+			return Err("End seeds are not supported with word tokens".to_owned());
+		}
+
+		let mut tokens: Vec<String> = Vec::new();
+		let seed = match &prediction_input.start_seed {
+			StartSeed::False => String::new(),
+			StartSeed::Custom(s) => {
+				let s = self.normalization.apply(s);
+				tokens = s.split_whitespace().map(str::to_owned).collect();
+				tokenize(&s).map(|token| self.vocabulary.encode(&token)).collect()
+			}
+			StartSeed::Random(random) => {
+				let models = self.get_random_models(prediction_input, rng);
+				Generator::random_seed(self.directed_model(&models[0], Direction::Tokens)?, *random, rng)?
+			}
+		};
+
+		let encoded = self.generate(prediction_input, seed, Direction::Tokens, rng)?;
+		for code in encoded.chars().skip(tokens.len()) {
+			let token = match self.vocabulary.decode(code) {
+				Some(token) => token.to_owned(),
+				None => self.rare_token(prediction_input, rng)?,
+			};
+			tokens.push(token);
+		}
+		Ok(tokens.join(" "))
+	}
+
+	/// Generates a word grapheme cluster by grapheme cluster (see `Tokenization::Graphemes`).
+	///
+	/// # Behavior
+	/// - A custom start seed is encoded with the shared cluster table.
+	/// - Clusters are drawn from the grapheme models like characters, then decoded,
+	///   so that a cluster is never split nor recombined with another.
+	/// - Lengths are counted in grapheme clusters; word constraints and
+	///   templates are only checked on the complete word.
+	///
+	/// # Errors
+	/// Returns an error if an end seed is set or if grapheme models were not trained.
+	fn generate_graphemes<R: Rng + ?Sized>(&self, prediction_input: &PredictionInput, rng: &mut R) -> Result<String, String> {
+		if prediction_input.end_seed != EndSeed::False {
+			return Err("End seeds are not supported with grapheme clusters".to_owned());
+		}
+		let table = self.graphemes.as_ref().ok_or_else(|| {
+			"Grapheme models are not trained: call train_graphemes before using grapheme clusters".to_owned()
+		})?;
+
+		let seed = match &prediction_input.start_seed {
+			StartSeed::False => String::new(),
+			StartSeed::Custom(s) => table.encode(&self.normalization.apply(s.strip_prefix(START_CHAR).unwrap_or(s))),
+			StartSeed::Random(random) => {
+				let models = self.get_random_models(prediction_input, rng);
+				Generator::random_seed(self.directed_model(&models[0], Direction::Graphemes)?, *random, rng)?
+			}
+		};
+
+		let encoded = self.generate(prediction_input, seed, Direction::Graphemes, rng)?;
+		Ok(table.decode(&encoded))
+	}
+
+	/// Returns a token replacing `RARE_TOKEN`, drawn from a weighted model.
+	fn rare_token<R: Rng + ?Sized>(&self, prediction_input: &PredictionInput, rng: &mut R) -> Result<String, String> {
+		for name in self.get_random_models(prediction_input, rng) {
+			let Some(token_model) = self.models.get(&name).and_then(MultiGramModel::tokens) else { continue };
+			let token = token_model.rare_token(
+				prediction_input.novel_tokens(),
+				prediction_input.max_n,
+				prediction_input.sampling(),
+				rng,
+			);
+			if let Some(token) = token {
+				return Ok(token);
+			}
+		}
+		Err("No rare token available for prediction".to_owned())
+	}
+
+	/// Generates the middle part of a word between a fixed `prefix` and `suffix`.
+	///
+	/// # Algorithm
+	/// - Draw `INFILL_CANDIDATES` continuations of `prefix` with the forward
+	///   models, each ending where the models would end the word.
+	/// - Every position of a continuation after `prefix` is a possible stop:
+	///   the suffix often fits before the point where the models end the word.
+	///   A stop whose middle part already ends with `suffix` is skipped, since
+	///   the suffix would be repeated (e.g. "pichuchu" for "pi" and "chu").
+	/// - Score each stop by how well it joins `suffix`: the probability of
+	///   reading every character of `suffix`, then the end of the word, after
+	///   it (see `join_log_prob`). Stops giving a word that would be retried
+	///   (outside the length bounds or, with `nb_try`, a training word) are
+	///   skipped, unless no stop is left.
+	/// - Pick one stop with a probability proportional to that score, so that
+	///   the join is natural rather than a plain concatenation.
+	///
+	/// # Returns
+	/// The complete word: `prefix`, middle part and `suffix`.
+	fn infill<R: Rng + ?Sized>(
+		&self,
+		prediction_input: &PredictionInput,
+		prefix: &str,
+		suffix: &str,
+		rng: &mut R,
+	) -> Result<String, String> {
+		let prefix_len = prefix.chars().count();
+		let suffix_len = suffix.chars().count();
+		let direction = Direction::Infill(suffix_len);
+		let folded_suffix = self.normalization.fold_case(suffix);
+		let mut stops: Vec<(String, f64)> = Vec::new();
+		let mut fitting: Vec<(String, f64)> = Vec::new();
+		for _ in 0..INFILL_CANDIDATES {
+			let start = self.generate(prediction_input, prefix.to_owned(), direction, rng)?;
+			let ends = start.char_indices().map(|(index, _)| index).chain(std::iter::once(start.len()));
+			for (len, end) in ends.enumerate().skip(prefix_len) {
+				// The suffix would be repeated: the stop before it is already a candidate
+				if !suffix.is_empty() && self.normalization.fold_case(&start[prefix.len()..end]).ends_with(&folded_suffix) {
+					continue;
+				}
+				let stop = start[..end].to_owned();
+				let score = self.join_log_prob(&stop, suffix, prediction_input);
+				let fits = if prediction_input.nb_try > 0 {
+					!self.is_rejected(&(stop.clone() + suffix), prediction_input, None)
+				} else {
+					prediction_input.accepts_length(len + suffix_len)
+				};
+				if fits {
+					fitting.push((stop.clone(), score));
+				}
+				stops.push((stop, score));
+			}
+		}
+		let mut candidates = if fitting.is_empty() { stops } else { fitting };
+
+		// Resample in linear space, shifted by the best score to avoid underflow
+		let best = candidates.iter().map(|(_, score)| *score).fold(f64::NEG_INFINITY, f64::max);
+		let weights: Vec<f64> = candidates.iter().map(|(_, score)| (score - best).exp()).collect();
+		let mut r = rng.random_range(0.0..weights.iter().sum::<f64>());
+		let index = weights
+			.iter()
+			.position(|weight| {
+				r -= weight;
+				r < 0.0
+			})
+			.unwrap_or(candidates.len() - 1);
+
+		let (start, _) = candidates.swap_remove(index);
+		Ok(start + suffix)
+	}
+
+	/// Returns the log-probability of reading `suffix`, then the end of the
+	/// word, right after `start`.
+	///
+	/// Each character probability is the mixture of the weighted models
+	/// (see `MultiGramModel::log_prob` for the backoff logic), with the
+	/// `max_n`, smoothing and `min_count` of `prediction_input`.
+	fn join_log_prob(&self, start: &str, suffix: &str, prediction_input: &PredictionInput) -> f64 {
+		let mut key = String::from(START_CHAR);
+		key.push_str(&self.normalization.fold_case(start));
+
+		let mut log_prob = 0.0;
+		for c in self.normalization.fold_case(suffix).chars().chain(std::iter::once(END_CHAR)) {
+			log_prob += self.mixture_probability(&key, c, prediction_input).ln();
+			key.push(c);
+		}
+		log_prob
+	}
+
+	/// Returns `P(c | key)` under the mixture of the weighted models, where `key`
+	/// starts with `START_CHAR`.
+	///
+	/// Each model probability is that of `MultiGramModel::char_probability`,
+	/// with the `max_n`, smoothing and `min_count` of `prediction_input`; the
+	/// step constraints and sampling filters do not apply.
+	fn mixture_probability(&self, key: &str, c: char, prediction_input: &PredictionInput) -> f64 {
+		prediction_input
+			.models_probability()
+			.filter(|(_, weight)| *weight > 0.0)
+			.filter_map(|(name, weight)| {
+				let model = self.models.get(name)?;
+				let probability = model.char_probability(
+					key,
+					c,
+					prediction_input.max_n,
+					prediction_input.smoothing,
+					prediction_input.sampling().min_count(),
+				);
+				Some(weight as f64 * probability)
+			})
+			.sum()
+	}
+
+	/// Returns the model named `name`, or the view of it used by `direction`:
+	/// its reversed model for `Direction::Backward`, its token model for
+	/// `Direction::Tokens`, its grapheme model for `Direction::Graphemes`
+	/// (the model itself if no cluster needs encoding).
+	///
+	/// # Errors
+	/// Returns an error if the model is missing, or if the required view was not trained.
+	fn directed_model(&self, name: &str, direction: Direction) -> Result<&MultiGramModel, String> {
+		let model = self.models.get(name).ok_or_else(|| "No model available for prediction".to_owned())?;
+		match direction {
+			Direction::Forward | Direction::Infill(_) => Ok(model),
+			Direction::Backward => model.reversed().ok_or_else(|| {
+				format!("Model '{}' has no reversed models: call train_reversed before using an end seed", name)
+			}),
+			Direction::Tokens => model.tokens().map(TokenModel::model).ok_or_else(|| {
+				format!("Model '{}' has no token models: call train_tokens before using word tokens", name)
+			}),
+			Direction::Graphemes => match &self.graphemes {
+				Some(table) if table.is_empty() => Ok(model),
+				Some(_) => model.graphemes().ok_or_else(|| {
+					format!("Model '{}' has no grapheme models: call train_graphemes before using grapheme clusters", name)
+				}),
+				None => Err("Grapheme models are not trained: call train_graphemes before using grapheme clusters".to_owned()),
+			},
+		}
+	}
+
+	/// Draws a random seed of up to `random` characters from `model`
+	/// (`0` = random size), without its leading `START_CHAR`.
+	///
+	/// # Errors
+	/// Returns an error if the model has no seed of the selected size.
+	fn random_seed<R: Rng + ?Sized>(model: &MultiGramModel, random: usize, rng: &mut R) -> Result<String, String> {
+		let n = if random == 0 {
+			rng.random_range(2..=model.size() + 1)
+		} else {
+			max(2, min(random, model.size() + 1))
+		};
+
+		let mut seed = match model.get_random_seed(n, rng) {
+			Some(s) => s.to_owned(),
+			None => return Err("No seed available for prediction".to_owned())
+		};
+		if seed.starts_with(START_CHAR) { seed.remove(0); }
+		Ok(seed)
+	}
+
+	/// Draws the next character from an explicit distribution.
+	///
+	/// Used instead of the fast sampling path when models are mixed or when
+	/// the candidates must be filtered at each step.
+	///
+	/// # Behavior
+	/// - Builds the distribution of `model` at order `n`, or the mixture of
+	///   every weighted model with `Blending::Mixture`.
+	/// - Applies the step constraints for the current `word`, masking the end
+	///   of the word if `duplicate` (the word is a training word).
+	/// - Applies the sampling parameters and draws from the result.
+	///
+	/// With `Direction::Backward`, `model`, `key` and `word` are right to left.
+	///
+	/// Returns `None` if no candidate remains, so the caller backs off.
+	#[allow(clippy::too_many_arguments)]
+	fn predict_distribution<R: Rng + ?Sized>(
+		&self,
+		model: &MultiGramModel,
+		key: &str,
+		word: &str,
+		n: usize,
+		duplicate: bool,
+		prediction_input: &PredictionInput,
+		direction: Direction,
+		rng: &mut R,
+	) -> Option<char> {
+		let min_count = prediction_input.sampling().min_count();
+		let mut distribution = match (prediction_input.blending, prediction_input.smoothing) {
+			(Blending::Mixture, _) => self.mixture_distribution(key, n, prediction_input, direction),
+			(Blending::PerCharacter, Smoothing::Backoff) => model.distribution(key, n, min_count)?,
+			(Blending::PerCharacter, Smoothing::WittenBell) => model.smoothed_distribution(key, n, min_count),
+		};
+		Generator::apply_step_constraints(&mut distribution, model, word, duplicate, prediction_input, direction);
+		distribution.shape(prediction_input.sampling());
+		distribution.sample(rng)
+	}
+
+	/// Filters and scales the candidate characters following `word`.
+	///
+	/// - Below `min_len`: the end transition is removed.
+	/// - At `max_len` (or the constrained length cap): only the end transition is kept.
+	/// - If `duplicate` (`word` is a training word): the end transition is removed.
+	/// - Word constraints: see `Constraints::apply`; steering toward a required
+	///   substring starts once `min_len` is reached.
+	/// - Template: only characters of the class expected at the current
+	///   position (as defined by `model`) are kept, and the end transition
+	///   only once the template is complete.
+	/// - With `end_scaling`: the end transition is scaled according to `len`
+	///   (see `PredictionInput::set_end_scaling`).
+	///
+	/// With `Direction::Backward`, `word` is right to left; with `Direction::Infill`,
+	/// the suffix still has to be appended. In both cases, word constraints and
+	/// templates are only checked on the complete word, and lengths include the suffix.
+	/// With `Direction::Tokens`, `word` is encoded: everything is checked on the complete word.
+	/// With `Direction::Graphemes`, `word` is encoded with one character per grapheme
+	/// cluster: lengths are in clusters, and word constraints and templates are
+	/// only checked on the complete word.
+	fn apply_step_constraints(
+		distribution: &mut Distribution,
+		model: &MultiGramModel,
+		word: &str,
+		duplicate: bool,
+		prediction_input: &PredictionInput,
+		direction: Direction,
+	) {
+		let forward = direction == Direction::Forward;
+		let len = match direction {
+			Direction::Infill(suffix_len) => word.chars().count() + suffix_len,
+			Direction::Forward | Direction::Backward | Direction::Graphemes => word.chars().count(),
+			Direction::Tokens => return,
+		};
+
+		if len < prediction_input.min_len() {
+			distribution.retain(|c| c != END_CHAR);
+		}
+		if prediction_input.effective_max_len().is_some_and(|max_len| len >= max_len) {
+			distribution.retain(|c| c == END_CHAR);
+		}
+		if duplicate {
+			distribution.retain(|c| c != END_CHAR);
+		}
+		if forward && prediction_input.constraints.is_active() {
+			prediction_input.constraints.apply(distribution, word, len >= prediction_input.min_len());
+		}
+		if forward && let Some(template) = &prediction_input.template {
+			let in_class = |class, c| model.in_class(class, c);
+			let positions = template.positions(word, &in_class);
+			let complete = template.is_complete(&positions);
+			distribution.retain(|c| {
+				if c == END_CHAR { complete } else { template.allows(&positions, c, &in_class) }
+			});
+		}
+
+		if let Some(factor) = prediction_input.end_scaling_factor(len) {
+			distribution.scale(END_CHAR, factor);
+		}
+	}
+
+	/// Builds the mixture of every weighted model's distribution at order `n`.
+	///
+	/// # Behavior
+	/// - Builds each model's distribution at order `n` (interpolated with
+	///   `Smoothing::WittenBell`). With `Smoothing::Backoff`, each model uses
+	///   the raw transitions of its own longest state for `key`, up to order `n`,
+	///   so that every weighted model contributes even when only some corpora
+	///   contain the whole key.
+	/// - Weights each one by its normalized intensity and sums them.
+	///
+	/// The result is empty if no weighted model has a state for `key`.
+	/// Uses the view of each model matching `direction` (see `directed_model`).
+	fn mixture_distribution(
+		&self,
+		key: &str,
+		n: usize,
+		prediction_input: &PredictionInput,
+		direction: Direction,
+	) -> Distribution {
+		let min_count = prediction_input.sampling().min_count();
+		let components = prediction_input
+			.models_probability()
+			.filter(|(_, weight)| *weight > 0.0)
+			.filter_map(|(name, weight)| {
+				let model = self.directed_model(name, direction).ok()?;
+				let distribution = match prediction_input.smoothing {
+					Smoothing::Backoff => (2..=n)
+						.rev()
+						.filter_map(|order| model.distribution(key, order, min_count))
+						.find(|distribution| !distribution.is_empty())?,
+					Smoothing::WittenBell => model.smoothed_distribution(key, n, min_count),
+				};
+				Some((distribution, weight as f64))
+			});
+
+		Distribution::mixture(components)
+	}
+
 	/// Compute next n
-	fn compute_n(prefix_size: usize, max_n: usize, randomness: f32) -> Result<usize, String> {
+	fn compute_n<R: Rng + ?Sized>(
+		prefix_size: usize,
+		max_n: usize,
+		randomness: f32,
+		rng: &mut R,
+	) -> Result<usize, String> {
 		let key_len = prefix_size;
 		let n = if max_n == 0 {
 			key_len + 1
 		} else {
 			max_n.min(key_len + 1)
 		};
-		Generator::compute_randomness(randomness, max_n, n)
+		Generator::compute_randomness(randomness, max_n, n, rng)
+	}
+
+	/// Lowers the n-gram size chosen by `compute_n` while the state of `key`
+	/// at that size barely branches (see `PredictionInput::set_adaptive_order`).
+	///
+	/// # Behavior
+	/// - With `Blending::PerCharacter`, the state of `model` is considered;
+	///   with `Blending::Mixture`, the states of every weighted model are
+	///   combined (distinct characters and observations are summed).
+	/// - Stops at 2, or at a size where no state exists, so that the usual
+	///   backoff applies.
+	///
+	/// Returns `n` unchanged if the order is not adaptive.
+	fn adapt_n(
+		&self,
+		model: &MultiGramModel,
+		key: &str,
+		mut n: usize,
+		prediction_input: &PredictionInput,
+		direction: Direction,
+	) -> usize {
+		let Some(min_continuations) = prediction_input.adaptive_order() else {
+			return n;
+		};
+		let min_count = prediction_input.sampling().min_count();
+
+		while n > 2 {
+			let mut continuations: BTreeMap<char, usize> = BTreeMap::new();
+			let mut add = |model: &MultiGramModel| {
+				for (c, count) in model.transitions(key, n).into_iter().flatten() {
+					if *count >= min_count {
+						*continuations.entry(*c).or_insert(0) += count;
+					}
+				}
+			};
+			match prediction_input.blending {
+				Blending::PerCharacter => add(model),
+				Blending::Mixture => prediction_input
+					.models_probability()
+					.filter(|(_, weight)| *weight > 0.0)
+					.filter_map(|(name, _)| self.directed_model(name, direction).ok())
+					.for_each(add),
+			}
+
+			let observations: usize = continuations.values().sum();
+			if continuations.is_empty() || (continuations.len() > 1 && observations >= min_continuations) {
+				break;
+			}
+			n -= 1;
+		}
+		n
 	}
 
 	/// Computes a possibly randomized n-gram size based on a randomness factor.
@@ -249,6 +857,7 @@ impl Generator {
 	///   a random n-gram size.
 	/// - `max`: Maximum n-gram size allowed (inclusive).
 	/// - `default`: Default n-gram size used when randomness is not applied.
+	/// - `rng`: Random source for both the draw and the selected size.
 	///
 	/// # Returns
 	/// - `Ok(usize)`: Either `default` or a randomly selected value in the range `[2, max]`.
@@ -258,16 +867,21 @@ impl Generator {
 	/// - With probability `randomness`, selects a random value in `[2, max]`.
 	/// - Otherwise, returns `default`.
 	/// - Ensures the returned n-gram size is always at least 2.
-	fn compute_randomness(randomness: f32, max: usize, default: usize) -> Result<usize, String> {
-		if randomness < 0.0 || randomness > 1.0 {
+	fn compute_randomness<R: Rng + ?Sized>(
+		randomness: f32,
+		max: usize,
+		default: usize,
+		rng: &mut R,
+	) -> Result<usize, String> {
+		if !(0.0..=1.0).contains(&randomness) {
 			Err(format!(
 				"randomness must be between 0.0 and 1.0, got {}",
 				randomness
 			))?;
 		}
 
-		if randomness > 0.0 && rand::rng().random_range(0.0..=1.0) <= randomness {
-			return Ok(rand::rng().random_range(2..=max.max(2)));
+		if randomness > 0.0 && rng.random_range(0.0..=1.0) <= randomness {
+			return Ok(rng.random_range(2..=max.max(2)));
 		}
 
 		Ok(default)
@@ -276,31 +890,610 @@ impl Generator {
 	/// Generates a sequence using multiple models while avoiding duplicates.
 	///
 	/// # Behavior
+	/// - Seeds a random generator from `prediction_input.rng_seed`, or from
+	///   the thread-local generator if no seed is set.
+	/// - Delegates to `predict_with_rng`.
+	pub fn predict(&self, prediction_input: &PredictionInput) -> Result<String, String> {
+		let mut rng = Generator::make_rng(prediction_input);
+		self.predict_with_rng(prediction_input, &mut rng)
+	}
+
+	/// Generates a sequence like `predict`, also avoiding the words already
+	/// produced in `session`, then records the returned word in it.
+	///
+	/// # Behavior
+	/// - Words of `session` are duplicates (case-insensitive), retried like
+	///   training words up to `nb_try` times.
+	/// - The returned word is inserted in `session`, even if every attempt failed.
+	///
+	/// # Errors
+	/// Same as `predict_with_rng`.
+	pub fn predict_in_session(
+		&self,
+		prediction_input: &PredictionInput,
+		session: &mut GenerationSession,
+	) -> Result<String, String> {
+		let mut rng = Generator::make_rng(prediction_input);
+		let word = self.predict_excluding(prediction_input, Some(session), &mut rng)?;
+		session.insert(&word);
+		Ok(word)
+	}
+
+	/// Generates `count` words, spreading the generation over threads.
+	///
+	/// # Behavior
+	/// - Each word passes the usual checks of `predict` (constraints,
+	///   length bounds, duplicates of training words). Unlike `predict`,
+	///   a candidate still failing them after `nb_try` retries is dropped
+	///   rather than returned.
+	/// - With `unique`, the words are also distinct from each other
+	///   (case-insensitive) and, with a positive `novelty.pairwise_distance`,
+	///   more than that many edits apart.
+	///
+	/// # Errors
+	/// Same as `predict_with_rng`; also returns an error if fewer than `count`
+	/// accepted words are found within `count * (nb_try + 1)` candidates.
+	///
+	/// # Notes
+	/// - With `prediction_input.rng_seed`, the batch is reproducible,
+	///   whatever the number of threads.
+	pub fn predict_many(&self, prediction_input: &PredictionInput, count: usize, unique: bool) -> Result<Vec<String>, String> {
+		self.predict_batch(prediction_input, count, unique, None)
+	}
+
+	/// Generates `count` distinct words like `predict_many`, also avoiding the
+	/// words already produced in `session`, then records them in it.
+	///
+	/// # Errors
+	/// Same as `predict_many`.
+	pub fn predict_many_in_session(
+		&self,
+		prediction_input: &PredictionInput,
+		count: usize,
+		session: &mut GenerationSession,
+	) -> Result<Vec<String>, String> {
+		let words = self.predict_batch(prediction_input, count, true, Some(session))?;
+		for word in &words {
+			session.insert(word);
+		}
+		Ok(words)
+	}
+
+	/// Implements `predict_many` and `predict_many_in_session`.
+	///
+	/// # Algorithm
+	/// - Candidates are generated in rounds, split among threads; candidate `i`
+	///   draws from its own random generator, seeded from the base seed and `i`.
+	/// - Candidates are then accepted in order, skipping those rejected by the
+	///   duplicate check and (with `unique`) those already in `session` or too
+	///   close to a word accepted earlier, so the result does not depend on
+	///   the number of threads.
+	/// - Each round draws as many candidates as words are still missing, at
+	///   most `BATCH_ROUND`; with `unique`, candidates avoid the words accepted
+	///   in previous rounds and the words of `session`. Round sizes only depend
+	///   on the accepted words, so a seeded batch is the same on every machine.
+	/// - Session words are only compared exactly; the pairwise distance is
+	///   checked against the words of this batch, in a BK-tree.
+	fn predict_batch(
+		&self,
+		prediction_input: &PredictionInput,
+		count: usize,
+		unique: bool,
+		session: Option<&GenerationSession>,
+	) -> Result<Vec<String>, String> {
+		let base_seed = prediction_input.rng_seed.unwrap_or_else(|| rand::rng().random());
+		let threads = num_cpus::get().max(1);
+		let max_candidates = count.saturating_mul(prediction_input.nb_try.saturating_add(1));
+		let distance = prediction_input.novelty.pairwise_distance();
+
+		// Words to avoid: those of the session, then the accepted ones
+		let mut excluded = GenerationSession::default();
+		for word in session.into_iter().flat_map(GenerationSession::words) {
+			excluded.insert(word);
+		}
+		// Lowercased words of this batch, for the pairwise distance
+		let mut accepted = BkTree::default();
+		let mut words: Vec<String> = Vec::new();
+		let mut next = 0;
+
+		while words.len() < count {
+			if next >= max_candidates {
+				return Err(format!("Only {} distinct words found after {} attempts", words.len(), next));
+			}
+			let round = (count - words.len()).min(BATCH_ROUND).min(max_candidates - next);
+			let indices: Vec<usize> = (next..next + round).collect();
+			let chunk_size = round.div_ceil(threads);
+			let excluded_ref = unique.then_some(&excluded);
+			let candidates: Vec<Result<String, String>> = thread::scope(|scope| {
+				let handles: Vec<_> = indices
+					.chunks(chunk_size)
+					.map(|chunk| {
+						scope.spawn(move || {
+							chunk
+								.iter()
+								.map(|i| {
+									let mut rng = ChaCha8Rng::seed_from_u64(base_seed.wrapping_add(*i as u64));
+									self.predict_excluding(prediction_input, excluded_ref, &mut rng)
+								})
+								.collect::<Vec<_>>()
+						})
+					})
+					.collect();
+				handles.into_iter().flat_map(|handle| handle.join().expect("Generation thread panicked")).collect()
+			});
+			next += round;
+
+			for candidate in candidates {
+				let word = candidate?;
+				if words.len() == count {
+					break;
+				}
+				if self.is_rejected(&word, prediction_input, None) {
+					continue;
+				}
+				if unique {
+					if excluded.contains(&word) {
+						continue;
+					}
+					let key: Vec<char> = word.to_lowercase().chars().collect();
+					if distance > 0 && accepted.has_within(&key, distance) {
+						continue;
+					}
+					accepted.insert(key);
+					excluded.insert(&word);
+				}
+				words.push(word);
+			}
+		}
+		Ok(words)
+	}
+
+	/// Returns the random generator seeded from `prediction_input.rng_seed`,
+	/// or from the thread-local generator if no seed is set.
+	///
+	/// ChaCha8 is a named algorithm: unlike `StdRng`, its output does not
+	/// change across `rand` releases, so a seed gives the same word everywhere.
+	fn make_rng(prediction_input: &PredictionInput) -> ChaCha8Rng {
+		match prediction_input.rng_seed {
+			Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+			None => ChaCha8Rng::from_rng(&mut rand::rng()),
+		}
+	}
+
+	/// Generates a sequence using multiple models while avoiding duplicates,
+	/// drawing every random decision from `rng`.
+	///
+	/// # Behavior
+	/// - Checks that the word constraints and template can be met by the weighted models.
 	/// - Calls `internal_predict`.
-	/// - Checks for duplicates against all loaded models up to `nb_try`.
-	/// - Returns the first non-duplicate or the last attempt if all fail.
-	pub fn predict(&mut self, prediction_input: &PredictionInput) -> Result<String, String> {
-		let mut word = self.internal_predict(prediction_input)?;
+	/// - Regenerates words violating the constraints or the template, up to
+	///   `constraints.max_attempts` times.
+	/// - Checks for duplicates against all loaded models (see `PredictionInput::novelty`;
+	///   exact copies may already be avoided while sampling), and the length
+	///   bounds, up to `nb_try`.
+	/// - Returns the first accepted word or the last attempt if all fail,
+	///   with the casing of `prediction_input.casing`.
+	///
+	/// # Errors
+	/// Returns an error if the constraints are infeasible, or if no word
+	/// satisfying them was found within the allowed attempts.
+	///
+	/// # Notes
+	/// - Given the same models, parameters and `rng` state, the generated
+	///   word is always the same.
+	/// - `prediction_input.rng_seed` is ignored; `rng` is used as is.
+	pub fn predict_with_rng<R: Rng + ?Sized>(
+		&self,
+		prediction_input: &PredictionInput,
+		rng: &mut R,
+	) -> Result<String, String> {
+		self.predict_excluding(prediction_input, None, rng)
+	}
+
+	/// Implements `predict_with_rng`, also rejecting the words of `session` if any.
+	fn predict_excluding<R: Rng + ?Sized>(
+		&self,
+		prediction_input: &PredictionInput,
+		session: Option<&GenerationSession>,
+		rng: &mut R,
+	) -> Result<String, String> {
+		let constraints = &prediction_input.constraints;
+		if constraints.is_active() {
+			constraints.check_feasible(|c| {
+				self.weighted_models(prediction_input).any(|model| model.knows_char(c))
+			})?;
+		}
+		if let Some(template) = &prediction_input.template {
+			for class in template.classes() {
+				if !self.weighted_models(prediction_input).any(|model| model.has_class(class)) {
+					return Err(format!(
+						"Template class '{}' is not defined for the selected models",
+						class
+					));
+				}
+			}
+		}
+
+		let mut word = self.internal_predict(prediction_input, rng)?;
 		let mut nb_try = prediction_input.nb_try;
+		let mut attempts = constraints.max_attempts;
 
 		loop {
-			let mut exists = false;
-			if nb_try > 0 {
-				for model in self.models.values() {
-					if model.check_if_exists(&word) {
-						exists = true;
-						break;
+			if !self.satisfies_constraints(&word, prediction_input) {
+				if attempts == 0 {
+					let mut description = constraints.describe();
+					if let Some(template) = &prediction_input.template {
+						if !description.is_empty() {
+							description.push_str(", ");
+						}
+						description.push_str(&format!("template '{}'", template.as_str()));
 					}
+					return Err(format!(
+						"No word satisfying the constraints ({}) found after {} attempts",
+						description,
+						constraints.max_attempts + 1
+					));
 				}
-				if exists {
-					word = self.internal_predict(prediction_input)?;
-					nb_try -= 1;
+				attempts -= 1;
+			} else if nb_try > 0 && self.is_rejected(&word, prediction_input, session) {
+				nb_try -= 1;
+			} else {
+				break;
+			}
+			word = self.internal_predict(prediction_input, rng)?;
+		}
+
+		Ok(self.apply_casing(&word, prediction_input))
+	}
+
+	/// Rewrites a generated word with the casing of `prediction_input.casing`.
+	fn apply_casing(&self, word: &str, prediction_input: &PredictionInput) -> String {
+		match prediction_input.casing {
+			Casing::Unchanged => word.to_owned(),
+			Casing::Lower => casing::restyle(word, |_, _| Some(Style::Lower)),
+			Casing::Title => casing::restyle(word, |_, _| Some(Style::Title)),
+			Casing::Upper => casing::restyle(word, |_, _| Some(Style::Upper)),
+			Casing::Learned => casing::restyle(word, |part, first| {
+				let votes = self
+					.learned_votes(prediction_input, |learned| learned.part_votes(part, first))
+					.or_else(|| self.learned_votes(prediction_input, LearnedCasing::overall_votes))?;
+				Some(LearnedCasing::majority(votes))
+			}),
+		}
+	}
+
+	/// Sums the casing votes of the weighted models, weighted by their intensities.
+	///
+	/// Returns `None` if no weighted model has votes.
+	fn learned_votes(
+		&self,
+		prediction_input: &PredictionInput,
+		votes: impl Fn(&LearnedCasing) -> Option<[f64; 3]>,
+	) -> Option<[f64; 3]> {
+		let mut total: Option<[f64; 3]> = None;
+		for (name, weight) in prediction_input.models_probability().filter(|(_, weight)| *weight > 0.0) {
+			let Some(model_votes) = self.models.get(name).and_then(|model| votes(model.casing())) else { continue };
+			let total = total.get_or_insert([0.0; 3]);
+			for (sum, vote) in total.iter_mut().zip(model_votes) {
+				*sum += weight as f64 * vote;
+			}
+		}
+		total
+	}
+
+	/// Finds the `n` most probable words that are not in any model's training set.
+	///
+	/// # Parameters
+	/// - `prediction_input`: generation settings. Models are combined as a
+	///   mixture weighted by their intensities; smoothing, sampling filters,
+	///   length bounds, constraints and template apply. Randomness is ignored.
+	/// - `n`: number of words to return.
+	/// - `beam_width`: number of partial words kept at each step.
+	///
+	/// # Returns
+	/// Up to `n` `(word, log-probability)` pairs, most probable first, with the
+	/// casing of `prediction_input.casing`.
+	/// The log-probability (natural log) includes the end of the word, given
+	/// the seed if any. It is the raw mixture probability, as given by `score`
+	/// for a single model: the sampling filters, length bounds, constraints and
+	/// template only prune candidates, they never raise a word's probability.
+	///
+	/// # Algorithm
+	/// - Beam search over the next-character distributions, starting from the
+	///   custom seed if any; each step is scored with `mixture_probability`.
+	/// - Where every candidate would keep the word on the path of a training
+	///   word, shorter keys are tried as well (see `best_candidates`), so that
+	///   long keys (e.g. `max_n = 0`) do not only lead to training words.
+	/// - Completed words are kept if they are new and satisfy the constraints.
+	/// - Stops when no partial word can beat the `n`-th best completed word,
+	///   since extending a word can only lower its probability.
+	///
+	/// # Errors
+	/// Returns an error if no model is loaded, `n` or `beam_width` is 0,
+	/// a random seed is requested, an end seed is set, units other than
+	/// characters are used, or if no new word is found.
+	pub fn best_words(
+		&self,
+		prediction_input: &PredictionInput,
+		n: usize,
+		beam_width: usize,
+	) -> Result<Vec<(String, f64)>, String> {
+		if self.models.is_empty() {
+			return Err("No models available for prediction".to_owned());
+		}
+		if n == 0 || beam_width == 0 {
+			return Err("Number of words and beam width must be at least 1".to_owned());
+		}
+
+		let seed = match &prediction_input.start_seed {
+			StartSeed::False => String::new(),
+			StartSeed::Custom(s) => self.normalization.apply(s.strip_prefix(START_CHAR).unwrap_or(s)),
+			StartSeed::Random(_) => return Err("Random seeds are not supported by beam search".to_owned()),
+		};
+		if prediction_input.end_seed != EndSeed::False {
+			return Err("End seeds are not supported by beam search".to_owned());
+		}
+		match prediction_input.tokenization {
+			Tokenization::Characters => {}
+			Tokenization::Words => return Err("Word tokens are not supported by beam search".to_owned()),
+			Tokenization::Graphemes => return Err("Grapheme clusters are not supported by beam search".to_owned()),
+		}
+		let max_len = prediction_input.effective_max_len().unwrap_or(CONSTRAINED_MAX_LEN);
+
+		let mut beams: Vec<(String, f64)> = vec![(seed, 0.0)];
+		let mut found: Vec<(String, f64)> = Vec::new();
+
+		while !beams.is_empty() {
+			let mut candidates: Vec<(String, f64)> = Vec::new();
+			for (word, log_prob) in &beams {
+				let mut key = String::from(START_CHAR);
+				key.push_str(&self.normalization.fold_case(word));
+				// The candidates only tell which characters may follow
+				for next_char in self.best_candidates(word, prediction_input) {
+					let log_prob = log_prob + self.mixture_probability(&key, next_char, prediction_input).ln();
+					if next_char != END_CHAR {
+						if word.chars().count() < max_len {
+							let mut extended = word.clone();
+							extended.push(next_char);
+							candidates.push((extended, log_prob));
+						}
+						continue;
+					}
+
+					let worst_kept = if found.len() >= n { found[n - 1].1 } else { f64::NEG_INFINITY };
+					if log_prob > worst_kept
+						&& !found.iter().any(|(w, _)| w == word)
+						&& self.satisfies_constraints(word, prediction_input)
+						&& !self.is_rejected(word, prediction_input, None)
+					{
+						found.push((word.clone(), log_prob));
+						found.sort_by(|a, b| b.1.total_cmp(&a.1));
+						found.truncate(n);
+					}
 				}
 			}
-			if !exists || nb_try <= 0 { break; }
+
+			candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+			candidates.truncate(beam_width);
+
+			// Extending a word only lowers its probability
+			if found.len() >= n && candidates.first().is_none_or(|(_, best)| *best <= found[n - 1].1) {
+				break;
+			}
+			beams = candidates;
 		}
 
-		Ok(word)
+		if found.is_empty() {
+			return Err("No new word found by beam search".to_owned());
+		}
+		Ok(found.into_iter().map(|(word, log_prob)| (self.apply_casing(&word, prediction_input), log_prob)).collect())
+	}
+
+	/// Returns the characters that beam search tries after `word`.
+	///
+	/// # Behavior
+	/// - Takes the candidates of the mixture of the weighted models at the
+	///   largest order allowed by `max_n`, after the step constraints and
+	///   sampling filters.
+	/// - Backs off to shorter keys while no candidate remains, or while every
+	///   candidate keeps `word` on the path of a training word (see
+	///   `Novelty::during_generation`): a state keyed by a whole training prefix
+	///   only continues training words, which the search would then discard.
+	/// - Returns the candidates of every order visited, in character order.
+	///
+	/// Template classes are those of the first weighted model.
+	fn best_candidates(&self, word: &str, prediction_input: &PredictionInput) -> Vec<char> {
+		let Some(model) = self.weighted_models(prediction_input).next() else {
+			return Vec::new();
+		};
+
+		let fold_accents = prediction_input.novelty.fold_accents();
+		let tries = self.models.values().map(|model| model.novelty_trie(fold_accents));
+		let paths = TriePaths::new(tries, fold_accents, word);
+		let leaves_training_words = |c: char| {
+			if c == END_CHAR {
+				return !paths.is_word();
+			}
+			let mut next = paths.clone();
+			next.push(c);
+			next.is_empty()
+		};
+
+		let mut key = word.to_owned();
+		key.insert(0, START_CHAR);
+		let key_len = key.chars().count();
+		let mut n = if prediction_input.max_n < 2 { key_len + 1 } else { prediction_input.max_n.min(key_len + 1) };
+
+		let mut candidates: BTreeSet<char> = BTreeSet::new();
+		while n >= 2 {
+			let mut distribution = self.mixture_distribution(&key, n, prediction_input, Direction::Forward);
+			Generator::apply_step_constraints(&mut distribution, model, word, false, prediction_input, Direction::Forward);
+			distribution.shape(prediction_input.sampling());
+			candidates.extend(distribution.probabilities().map(|(c, _)| c));
+			if candidates.iter().any(|c| leaves_training_words(*c)) {
+				break;
+			}
+			n -= 1;
+		}
+		candidates.into_iter().collect()
+	}
+
+	/// Scores a word under each loaded model.
+	///
+	/// Uses the `max_n`, `smoothing` and `min_count` settings of `prediction_input`
+	/// (see `MultiGramModel::log_prob` for the backoff logic).
+	///
+	/// # Returns
+	/// One `(model_name, score)` pair per loaded model, in name order.
+	pub fn score(&self, word: &str, prediction_input: &PredictionInput) -> Vec<(String, WordScore)> {
+		self.models
+			.iter()
+			.map(|(name, model)| {
+				let score = model.score(
+					word,
+					prediction_input.max_n,
+					prediction_input.smoothing,
+					prediction_input.sampling().min_count(),
+				);
+				(name.clone(), score)
+			})
+			.collect()
+	}
+
+	/// Tells which corpus a word most likely comes from.
+	///
+	/// Scores the word under each loaded model with Witten-Bell interpolation
+	/// over `CLASSIFY_ORDER`-grams, then normalizes the likelihoods assuming
+	/// every model is equally likely a priori.
+	///
+	/// # Returns
+	/// `(model_name, probability)` pairs summing to 1, most probable first
+	/// (ties in name order). Empty if no model is loaded.
+	pub fn classify(&self, word: &str) -> Vec<(String, f64)> {
+		let log_likelihoods: Vec<(String, f64)> = self
+			.models
+			.iter()
+			.map(|(name, model)| (name.clone(), model.score(word, CLASSIFY_ORDER, Smoothing::WittenBell, 0).total))
+			.collect();
+
+		// Log-sum-exp, shifted by the maximum to avoid underflow on long words
+		let max_log = log_likelihoods.iter().map(|(_, log)| *log).fold(f64::NEG_INFINITY, f64::max);
+		let sum: f64 = log_likelihoods.iter().map(|(_, log)| (log - max_log).exp()).sum();
+
+		let mut posterior: Vec<(String, f64)> = log_likelihoods
+			.into_iter()
+			.map(|(name, log)| (name, (log - max_log).exp() / sum))
+			.collect();
+		posterior.sort_by(|(a_name, a), (b_name, b)| b.total_cmp(a).then_with(|| a_name.cmp(b_name)));
+		posterior
+	}
+
+	/// Returns the models with a positive weight, in name order.
+	fn weighted_models<'a>(
+		&'a self,
+		prediction_input: &'a PredictionInput,
+	) -> impl Iterator<Item = &'a MultiGramModel> {
+		prediction_input
+			.models_probability()
+			.filter(|(_, weight)| *weight > 0.0)
+			.filter_map(|(name, _)| self.models.get(name))
+	}
+
+	/// Returns `true` if a complete word satisfies the word constraints and
+	/// follows the template with the classes of at least one weighted model.
+	fn satisfies_constraints(&self, word: &str, prediction_input: &PredictionInput) -> bool {
+		if !prediction_input.constraints.is_satisfied(word) {
+			return false;
+		}
+		match &prediction_input.template {
+			Some(template) => self
+				.weighted_models(prediction_input)
+				.any(|model| template.matches(word, &|class, c| model.in_class(class, c))),
+			None => true,
+		}
+	}
+
+	/// Defines (or redefines) a template character class for one model.
+	///
+	/// # Errors
+	/// Returns an error if the model is not loaded or the class is invalid
+	/// (see `MultiGramModel::set_character_class`).
+	pub fn set_character_class(&mut self, model: &str, class: char, chars: &str) -> Result<(), String> {
+		match self.models.get_mut(model) {
+			Some(model) => model.set_character_class(class, chars),
+			None => Err(format!("Model {} not found", model)),
+		}
+	}
+
+	/// Trains the word-token models of every loaded model, enabling `Tokenization::Words`.
+	///
+	/// Builds a vocabulary shared by every model, so that they can be mixed.
+	/// Does nothing if already trained; must be called again after models are merged.
+	///
+	/// # Errors
+	/// Returns an error if the corpora have too many distinct tokens.
+	pub fn train_tokens(&mut self) -> Result<(), String> {
+		if self.models.values().all(|model| model.tokens().is_some()) {
+			return Ok(());
+		}
+		self.vocabulary = Vocabulary::build(self.models.values())?;
+		for model in self.models.values_mut() {
+			model.train_tokens(&self.vocabulary)?;
+		}
+		Ok(())
+	}
+
+	/// Trains the grapheme-cluster models of every loaded model, enabling `Tokenization::Graphemes`.
+	///
+	/// Builds a cluster table shared by every model, so that they can be mixed.
+	/// If no corpus has multi-character clusters, the character models are used
+	/// as is. Does nothing if already trained; must be called again after models are merged.
+	///
+	/// # Errors
+	/// Returns an error if the corpora have too many distinct multi-character clusters.
+	pub fn train_graphemes(&mut self) -> Result<(), String> {
+		if self.graphemes.is_some() {
+			return Ok(());
+		}
+		let table = Graphemes::build(self.models.values().flat_map(MultiGramModel::sentences), &self.normalization)?;
+		if !table.is_empty() {
+			for model in self.models.values_mut() {
+				model.train_graphemes(&table)?;
+			}
+		}
+		self.graphemes = Some(table);
+		Ok(())
+	}
+
+	/// Trains the right-to-left models of every loaded model, enabling `EndSeed`.
+	///
+	/// # Errors
+	/// Returns an error if a model fails to train (see `MultiGramModel::train_reversed`).
+	pub fn train_reversed(&mut self) -> Result<(), String> {
+		for model in self.models.values_mut() {
+			model.train_reversed()?;
+		}
+		Ok(())
+	}
+
+	/// Returns `true` if a generated word must be retried.
+	///
+	/// A word is rejected if it is outside the length bounds, if it is a
+	/// duplicate of a word of one of the loaded models (see `PredictionInput::novelty`),
+	/// or if it was already produced in `session`.
+	/// With `Tokenization::Graphemes`, lengths are counted in grapheme clusters.
+	fn is_rejected(&self, word: &str, prediction_input: &PredictionInput, session: Option<&GenerationSession>) -> bool {
+		let len = match prediction_input.tokenization {
+			Tokenization::Graphemes => word.graphemes(true).count(),
+			Tokenization::Characters | Tokenization::Words => word.chars().count(),
+		};
+		if !prediction_input.accepts_length(len) {
+			return true;
+		}
+		if session.is_some_and(|session| session.contains(word)) {
+			return true;
+		}
+		self.models.values().any(|model| model.is_duplicate(word, &prediction_input.novelty))
 	}
 
 	/// Merges another `Generator` into this one.
@@ -309,6 +1502,8 @@ impl Generator {
 	/// - Existing models are merged; new ones are cloned.
 	/// - Model names are appended.
 	/// - Does not merge sentences internally (handled at model level).
+	/// - Drops the word-token and grapheme models, whose shared tables change
+	///   (see `train_tokens` and `train_graphemes`).
 	pub fn merge(&mut self, other: &Self) -> Result<(), String> {
 		for (k, t) in &other.models {
 			if let Some(existing) = self.models.get_mut(k) {
@@ -317,6 +1512,127 @@ impl Generator {
 				self.models.insert(k.clone(), t.clone());
 			}
 		}
+		for model in self.models.values_mut() {
+			model.clear_shared_views();
+		}
+		self.vocabulary = Vocabulary::default();
+		self.graphemes = None;
 		Ok(())
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Builds a generator with a single model named `name` learned from `lines`.
+	fn generator(name: &str, lines: &[&str]) -> Generator {
+		let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+		let mut model = MultiGramModel::from_lines(&lines).unwrap();
+		model.set_name(name);
+		Generator::from_models(vec![model])
+	}
+
+	#[test]
+	fn best_words_log_probs_match_score() {
+		let app = generator("test", &["marseille", "martigues", "marignane", "maurepas", "mulhouse", "metz", "melun", "meaux"]);
+		let mut input = app.make_prediction_input();
+		input.max_n = 3;
+		input.set_length_bounds(0, Some(6)).unwrap();
+		let words = app.best_words(&input, 5, 20).unwrap();
+		assert!(!words.is_empty());
+		for (word, log_prob) in words {
+			let (_, score) = &app.score(&word, &input)[0];
+			assert!((log_prob - score.total).abs() < 1e-9, "{}: {} != {}", word, log_prob, score.total);
+		}
+	}
+
+	#[test]
+	fn best_words_finds_new_words_with_whole_prefix_keys() {
+		let app = generator("test", &["marseille", "martigues", "marignane", "maurepas", "mulhouse", "metz", "melun", "meaux"]);
+		let mut input = app.make_prediction_input();
+		input.set_length_bounds(4, None).unwrap();
+		let words = app.best_words(&input, 3, 20).unwrap();
+		assert_eq!(words.len(), 3);
+		for (word, _) in words {
+			assert!(!app.is_rejected(&word, &input, None), "{}", word);
+		}
+	}
+
+	#[test]
+	fn seeded_prediction_is_pinned() {
+		let app = generator("test", &["marseille", "martigues", "marignane", "maurepas", "mulhouse"]);
+		let mut input = app.make_prediction_input();
+		input.max_n = 3;
+		let words: Vec<String> = (0..4)
+			.map(|seed| {
+				input.rng_seed = Some(seed);
+				app.predict(&input).unwrap()
+			})
+			.collect();
+		assert_eq!(words, ["marse", "maurepas", "martigues", "maurepas"]);
+	}
+
+	#[test]
+	fn infill_does_not_repeat_the_suffix() {
+		let app = generator("test", &["pikachu", "pichu", "raichu", "pikipek", "piplup", "pidgeotto", "machoke", "kakuna"]);
+		let mut input = app.make_prediction_input();
+		input.start_seed = StartSeed::Custom("pi".to_owned());
+		input.end_seed = EndSeed::Custom("chu".to_owned());
+		// With retries, training words are avoided: the suffix must still not be repeated
+		for nb_try in [0, 10] {
+			input.nb_try = nb_try;
+			for seed in 0..20 {
+				input.rng_seed = Some(seed);
+				let word = app.predict(&input).unwrap();
+				assert!(word.starts_with("pi") && word.ends_with("chu"), "{}", word);
+				assert!(!word.ends_with("chuchu"), "{}", word);
+			}
+		}
+	}
+
+	#[test]
+	fn batches_drop_training_copies() {
+		let app = generator("test", &["marseille", "martigues", "marignane", "maurepas", "mulhouse", "metz", "melun", "meaux"]);
+		let mut input = app.make_prediction_input();
+		input.max_n = 3;
+		input.nb_try = 10;
+		input.rng_seed = Some(0);
+		for word in app.predict_many(&input, 5, true).unwrap() {
+			assert!(!app.is_rejected(&word, &input, None), "{}", word);
+		}
+
+		// A corpus of a single word can only be copied
+		let app = generator("copy", &["ab"]);
+		let mut input = app.make_prediction_input();
+		input.nb_try = 2;
+		assert!(app.predict_many(&input, 3, false).is_err());
+	}
+
+	#[test]
+	fn seeded_grapheme_prediction_is_stable_across_loads() {
+		let directory = std::env::temp_dir().join(format!("rs-gen-graphemes-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		let words = [
+			"ce\u{301}ra\u{300}n", "mo\u{302}nu\u{308}", "i\u{301}c\u{327}o", "n\u{303}a\u{300}te\u{301}",
+			"u\u{308}ro\u{302}c\u{327}", "a\u{300}n\u{303}ui\u{301}", "to\u{302}e\u{301}nu\u{308}", "c\u{327}a\u{300}mi\u{301}",
+		];
+		std::fs::write(directory.join("accents.dat"), words.join("\n")).unwrap();
+
+		let predictions = || {
+			let mut app = Generator::new(&directory).unwrap();
+			app.train_graphemes().unwrap();
+			let mut input = app.make_prediction_input();
+			input.tokenization = Tokenization::Graphemes;
+			(0..8)
+				.map(|seed| {
+					input.rng_seed = Some(seed);
+					app.predict(&input).unwrap()
+				})
+				.collect::<Vec<String>>()
+		};
+		let first = predictions();
+		let second = predictions();
+		std::fs::remove_dir_all(&directory).unwrap();
+		assert_eq!(first, second);
+	}
+}
@@ -0,0 +1,98 @@
+use crate::model::normalization::Normalization;
+use std::collections::{BTreeMap, BTreeSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Private-use range holding the grapheme codes (Basic Multilingual Plane).
+const CODE_RANGE: (u32, u32) = (0xE000, 0xF8FF);
+
+/// Bidirectional mapping between multi-character grapheme clusters and the
+/// characters encoding them.
+///
+/// Grapheme models reuse the character n-gram machinery: each cluster made of
+/// several characters (a letter with combining accents, a flag, an emoji
+/// sequence...) is encoded as one private-use character, so that it is
+/// learned and generated as a single unit. Single-character clusters are
+/// kept as they are. The table is shared by every model of a generator,
+/// so that grapheme models can be mixed.
+///
+/// # Invariants
+/// - `clusters[i]` is encoded by `CODE_RANGE.0 + i`
+/// - `codes` is the inverse of `clusters`
+/// - Clusters are case-folded like the models (see `Normalization::fold_case`)
+///   and have at least two characters
+/// - Codes are assigned in cluster order, so the same corpora always give
+///   the same table (required for seeded reproducibility)
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Graphemes {
+	codes: BTreeMap<String, char>,
+	clusters: Vec<String>,
+
+	/// Case handling of the models (only `case_sensitive` is used).
+	normalization: Normalization,
+}
+
+impl Graphemes {
+	/// Builds the table of the multi-character clusters found in `sentences`.
+	///
+	/// Clusters are case-folded following `normalization`, like the models
+	/// trained with the table.
+	///
+	/// # Errors
+	/// Returns an error if there are more distinct clusters than available codes.
+	pub(crate) fn build<'a>(
+		sentences: impl Iterator<Item = &'a String>,
+		normalization: &Normalization,
+	) -> Result<Self, String> {
+		let normalization = normalization.case_only();
+		let mut clusters = BTreeSet::new();
+		for sentence in sentences {
+			for cluster in normalization.fold_case(sentence).graphemes(true) {
+				if cluster.chars().nth(1).is_some() {
+					clusters.insert(cluster.to_owned());
+				}
+			}
+		}
+
+		let mut table = Self { normalization, ..Self::default() };
+		for cluster in clusters {
+			let code = char::from_u32(CODE_RANGE.0 + table.clusters.len() as u32)
+				.filter(|code| (*code as u32) <= CODE_RANGE.1)
+				.ok_or_else(|| "Too many distinct grapheme clusters to encode".to_owned())?;
+			table.codes.insert(cluster.clone(), code);
+			table.clusters.push(cluster);
+		}
+		Ok(table)
+	}
+
+	/// Returns `true` if no cluster needs encoding (characters and graphemes coincide).
+	pub(crate) fn is_empty(&self) -> bool {
+		self.clusters.is_empty()
+	}
+
+	/// Case-folds `text` like the table and encodes its multi-character clusters.
+	///
+	/// Clusters missing from the table are kept as they are.
+	pub(crate) fn encode(&self, text: &str) -> String {
+		self.normalization
+			.fold_case(text)
+			.graphemes(true)
+			.map(|cluster| match self.codes.get(cluster) {
+				Some(code) => code.to_string(),
+				None => cluster.to_owned(),
+			})
+			.collect()
+	}
+
+	/// Decodes the clusters of an encoded text.
+	pub(crate) fn decode(&self, text: &str) -> String {
+		text.chars()
+			.map(|c| {
+				let index = (c as u32).wrapping_sub(CODE_RANGE.0) as usize;
+				match self.clusters.get(index) {
+					Some(cluster) => cluster.clone(),
+					None => c.to_string(),
+				}
+			})
+			.collect()
+	}
+}
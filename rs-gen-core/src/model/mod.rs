@@ -31,6 +31,54 @@ pub mod ngram_model;
 /// This module is not exposed publicly.
 mod state;
 
+/// Internal weighted distribution over next characters.
+///
+/// Supports reshaping (temperature, top-k, top-p) and weighted sampling.
+/// This module is not exposed publicly.
+mod distribution;
+
+/// Word-token models for multi-word corpora.
+///
+/// Encodes each token as one character to reuse the n-gram machinery.
+/// This module is not exposed publicly.
+mod tokens;
+
+/// Grapheme-cluster models.
+///
+/// Encodes each multi-character cluster as one character to reuse the n-gram machinery.
+/// This module is not exposed publicly.
+mod graphemes;
+
+/// Casing styles of word parts, learned from a corpus or imposed on output.
+///
+/// This module is not exposed publicly.
+mod casing;
+
+/// Unicode normalization, accent folding and alphabet restriction of learned text.
+pub mod normalization;
+
+/// Duplicate check of generated words against training words (exact or fuzzy).
+pub mod novelty;
+
+/// Words already produced across several generations, treated as duplicates.
+pub mod session;
+
+/// Constraints on generated words (suffix, substrings, regular expression).
+///
+/// Honored during sampling by masking and steering transitions.
+pub mod constraints;
+
+/// Consonant/vowel skeleton templates (e.g. `CVCCV`).
+///
+/// Masks each state's transitions to the class allowed at each position.
+pub mod template;
+
+/// Log-probability and perplexity of a word under a model.
+pub mod score;
+
+/// Held-out evaluation of a generation configuration.
+pub mod evaluation;
+
 /// Internal prediction configuration structure.
 ///
 /// Stores generation parameters such as randomness, retry limits,
@@ -1,25 +1,99 @@
+use super::casing::LearnedCasing;
+use super::distribution::Distribution;
 use super::ngram_model::NGramModel;
+use super::normalization::Normalization;
+use super::novelty::{Novelty, NoveltyIndex, Trie};
+use super::prediction_input::{Sampling, Smoothing};
+use super::score::WordScore;
+use super::graphemes::Graphemes;
+use super::template::default_class;
+use super::tokens::{TokenModel, Vocabulary};
 use crate::io::{build_output_path, get_filename, read_file};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::Path;
 use std::sync::mpsc;
 use std::thread;
 
-pub(crate) const START_CHAR: char = '<';
-pub(crate) const END_CHAR: char = '>';
+/// Marks the start of a sentence in the n-gram keys.
+///
+/// Sentinels are Unicode noncharacters, reserved for internal use and never
+/// expected in text, so that any corpus character (including `<` and `>`)
+/// can be learned and generated. They are removed from learned sentences.
+pub(crate) const START_CHAR: char = '\u{FDD0}';
+/// Marks the end of a sentence in the n-gram transitions (see `START_CHAR`).
+pub(crate) const END_CHAR: char = '\u{FDD1}';
+
+/// Start sentinel of the binary caches written by earlier versions.
+const LEGACY_START_CHAR: char = '<';
+/// End sentinel of the binary caches written by earlier versions.
+const LEGACY_END_CHAR: char = '>';
+
+/// Header of the binary caches using `START_CHAR` and `END_CHAR`.
+///
+/// Earlier caches have no header. They cannot start with these bytes: a
+/// legacy cache starts with the number of n-gram orders, then the first
+/// order twice (as the map key and inside the model), which 'S' and 'G' are not.
+const CACHE_HEADER: &[u8] = b"RSGEN2";
+
+/// Probability mass that a state leaves to the characters it has never seen
+/// when a word is scored (see `backoff_distribution`). Generation never draws
+/// them from that state, so it is kept small: seen characters are scored at
+/// `1 - UNSEEN_MASS` times their generation probability.
+const UNSEEN_MASS: f64 = 1e-3;
 
 /// Top-level n-gram model containing multiple n-gram sizes and metadata for sequence generation.
 ///
 /// # Responsibilities
 /// - Store `ngrams`: a map from n-gram size to its corresponding `NGramModel`.
-/// - Track `sentences` already added to avoid duplicates.
+/// - Track `sentences` already added, indexed to detect duplicates quickly.
 /// - Maintain `name` of the model (from a file).
+/// - Normalize learned sentences and looked-up words (see `Normalization`).
+/// - Keep the unigram counts used as the base of smoothed predictions.
+/// - Hold the character classes used by templates (`C`, `V`, ...).
+/// - Keep the casing styles of the corpus for `Casing::Learned`.
+/// - Optionally hold a right-to-left copy of itself for backward generation.
+/// - Optionally hold a word-token view of itself for multi-word corpora.
+/// - Optionally hold a grapheme-cluster view of itself.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MultiGramModel {
 	ngrams: HashMap<usize, NGramModel>,
 	sentences: HashSet<String>,
-	name: String
+	name: String,
+	/// Character counts over the whole corpus. Not serialized; rebuilt by
+	/// `freeze`, kept up to date by `add_sentence` and `merge`.
+	#[serde(skip)]
+	unigram: BTreeMap<char, usize>,
+	/// Custom character classes for templates, overriding the built-in ones.
+	/// Not serialized; configured at runtime.
+	#[serde(skip)]
+	classes: BTreeMap<char, BTreeSet<char>>,
+	/// Normalization applied to learned sentences and looked-up words.
+	/// Not serialized; part of the binary cache name instead (see `with_normalization`).
+	#[serde(skip)]
+	normalization: Normalization,
+	/// Casing styles of the sentences. Not serialized; rebuilt by `freeze`,
+	/// kept up to date by `add_sentence` and `merge`.
+	#[serde(skip)]
+	casing: LearnedCasing,
+	/// Lowercased sentences for the duplicate check. Not serialized; rebuilt
+	/// by `set_normalization`, kept up to date by `add_sentence` and `merge`.
+	#[serde(skip)]
+	index: NoveltyIndex,
+	/// Model trained on the reversed sentences (from `END_CHAR` backward),
+	/// where `START_CHAR` marks the end of the original word.
+	/// Not serialized; built on demand by `train_reversed`.
+	#[serde(skip)]
+	reversed: Option<Box<MultiGramModel>>,
+	/// Models over whitespace-separated tokens.
+	/// Not serialized; built on demand by `train_tokens`, dropped on modification.
+	#[serde(skip)]
+	tokens: Option<Box<TokenModel>>,
+	/// Model over encoded grapheme clusters (see `Graphemes`).
+	/// Not serialized; built on demand by `train_graphemes`, dropped on modification.
+	#[serde(skip)]
+	graphemes: Option<Box<MultiGramModel>>
 }
 
 impl MultiGramModel {
@@ -39,7 +113,15 @@ impl MultiGramModel {
 		Self {
 			ngrams: HashMap::new(),
 			sentences: HashSet::new(),
-			name: "".to_owned()
+			name: "".to_owned(),
+			unigram: BTreeMap::new(),
+			classes: BTreeMap::new(),
+			normalization: Normalization::default(),
+			casing: LearnedCasing::default(),
+			index: NoveltyIndex::default(),
+			reversed: None,
+			tokens: None,
+			graphemes: None
 		}
 	}
 
@@ -55,42 +137,210 @@ impl MultiGramModel {
 	///
 	/// # Notes
 	/// - Uses a binary cache (`postcard`) for faster loading if available.
+	/// - The returned model is frozen and ready for fast sampling.
+	/// - Sentences are learned as written (see `with_normalization`).
 	pub fn new<P: AsRef<Path>>(filepath: P) -> Result<Self, Box<dyn std::error::Error>> {
-		let binary_data_path = build_output_path(&filepath, "bin")?;
-		let mut model;
+		Self::with_normalization(filepath, &Normalization::default())
+	}
+
+	/// Loads a `MultiGramModel` like `new`, normalizing every sentence before
+	/// it is learned.
+	///
+	/// # Parameters
+	/// - `filepath`: Input text file path.
+	/// - `normalization`: applied to learned sentences, and later to the words
+	///   checked by `check_if_exists` and scored.
+	///
+	/// # Notes
+	/// - The binary cache depends on the normalization: a non-default one is
+	///   cached next to the default one, e.g. `french.nfc-fold.bin`.
+	/// - Caches written by earlier versions (with `<` and `>` sentinels) are
+	///   converted to the current sentinels and rewritten. Corpora containing
+	///   `<` or `>` were learned incorrectly by those versions: delete their
+	///   caches to rebuild them.
+	pub fn with_normalization<P: AsRef<Path>>(
+		filepath: P,
+		normalization: &Normalization,
+	) -> Result<Self, Box<dyn std::error::Error>> {
+		let extension = match normalization.cache_tag() {
+			Some(tag) => format!("{}.bin", tag),
+			None => "bin".to_owned(),
+		};
+		let binary_data_path = build_output_path(&filepath, &extension)?;
+		let mut model: MultiGramModel;
 		if binary_data_path.exists() {
-			let bytes = std::fs::read(binary_data_path)?;
-			model = postcard::from_bytes(&bytes)?;
+			let bytes = std::fs::read(&binary_data_path)?;
+			model = match bytes.strip_prefix(CACHE_HEADER) {
+				Some(bytes) => postcard::from_bytes(bytes)?,
+				None => {
+					let mut legacy: MultiGramModel = postcard::from_bytes(&bytes)?;
+					legacy.replace_legacy_sentinels();
+					legacy.write_cache(&binary_data_path)?;
+					legacy
+				}
+			};
 		} else {
-			model = Self::read_database_file(&filepath, binary_data_path)?;
+			model = Self::read_database_file(&filepath, binary_data_path, normalization)?;
 		}
 		model.name = get_filename(filepath)?;
+		model.set_normalization(normalization);
+		model.freeze();
 		Ok(model)
 	}
 
-	/// Reads a raw text file, splits lines into chunks, builds partial models in parallel,
-	/// merges them into a final `MultiGramModel`, and serializes it.
+	/// Freezes every n-gram model for fast sampling, one thread per model,
+	/// and rebuilds the unigram counts and casing styles.
+	///
+	/// Must be called once learning is complete; adding sentences or merging
+	/// afterwards leaves the changed states unfrozen (slower to sample, but
+	/// correct) until the next call.
+	pub fn freeze(&mut self) {
+		self.unigram = match self.ngrams.get(&2) {
+			Some(model) => model.transition_totals(),
+			None => BTreeMap::new(),
+		};
+		self.casing = LearnedCasing::learn(self.sentences.iter());
+
+		thread::scope(|scope| {
+			for model in self.ngrams.values_mut() {
+				scope.spawn(move || model.freeze());
+			}
+		});
+
+		if let Some(reversed) = &mut self.reversed {
+			reversed.freeze();
+		}
+	}
+
+	/// Trains right-to-left models on the reversed sentences, so that words
+	/// can be generated backward from their ending (see `EndSeed`).
+	///
+	/// # Notes
+	/// - Built from the stored sentences; does nothing if already trained.
+	/// - Kept up to date by `add_sentence` and `merge`; frozen with this model.
+	/// - Not cached on disk: must be called again after loading.
+	pub fn train_reversed(&mut self) -> Result<(), String> {
+		if self.reversed.is_some() {
+			return Ok(());
+		}
+		let lines: Vec<String> = self.sentences.iter().map(|sentence| Self::reverse(sentence)).collect();
+		let mut reversed = Self::from_normalized_lines(&lines, &self.normalization.case_only())?;
+		reversed.name = self.name.clone();
+		reversed.freeze();
+		self.reversed = Some(Box::new(reversed));
+		Ok(())
+	}
+
+	/// Trains word-token models on the stored sentences (see `TokenModel`).
+	///
+	/// `vocabulary` must be shared by every model that is mixed with this one.
+	pub(crate) fn train_tokens(&mut self, vocabulary: &Vocabulary) -> Result<(), String> {
+		self.tokens = Some(Box::new(TokenModel::train(self.sentences.iter(), vocabulary, &self.name)?));
+		Ok(())
+	}
+
+	/// Returns the word-token models, if trained.
+	pub(crate) fn tokens(&self) -> Option<&TokenModel> {
+		self.tokens.as_deref()
+	}
+
+	/// Trains grapheme-cluster models on the stored sentences, encoded with `table`.
+	///
+	/// `table` must be shared by every model that is mixed with this one.
+	pub(crate) fn train_graphemes(&mut self, table: &Graphemes) -> Result<(), String> {
+		let lines: Vec<String> = self.sentences.iter().map(|sentence| table.encode(sentence)).collect();
+		let mut graphemes = Self::from_normalized_lines(&lines, &self.normalization.case_only())?;
+		graphemes.name = self.name.clone();
+		graphemes.freeze();
+		self.graphemes = Some(Box::new(graphemes));
+		Ok(())
+	}
+
+	/// Returns the grapheme-cluster models, if trained.
+	pub(crate) fn graphemes(&self) -> Option<&MultiGramModel> {
+		self.graphemes.as_deref()
+	}
+
+	/// Drops the word-token and grapheme-cluster models, which must be trained again.
+	pub(crate) fn clear_shared_views(&mut self) {
+		self.tokens = None;
+		self.graphemes = None;
+	}
+
+	/// Returns the sentences the model was trained on.
+	pub(crate) fn sentences(&self) -> impl Iterator<Item = &String> {
+		self.sentences.iter()
+	}
+
+	/// Returns the right-to-left model, if trained.
+	pub(crate) fn reversed(&self) -> Option<&MultiGramModel> {
+		self.reversed.as_deref()
+	}
+
+	/// Reverses a string, character by character.
+	fn reverse(s: &str) -> String {
+		s.chars().rev().collect()
+	}
+
+	/// Reads a raw text file, builds the model from its lines and serializes it.
 	fn read_database_file<PF, PB>(
 		filename: PF,
 		binary_data_path: PB,
+		normalization: &Normalization,
 	) -> Result<MultiGramModel, Box<dyn std::error::Error>>
 	where
 		PF: AsRef<Path>,
 		PB: AsRef<Path>,
 	{
 		let lines = read_file(&filename)?;
+		let final_model = Self::from_normalized_lines(&lines, normalization)?;
+		final_model.write_cache(binary_data_path)?;
+		Ok(final_model)
+	}
+
+	/// Serializes the model into a binary cache, after `CACHE_HEADER`.
+	fn write_cache<P: AsRef<Path>>(&self, binary_data_path: P) -> Result<(), Box<dyn std::error::Error>> {
+		let mut bytes = CACHE_HEADER.to_vec();
+		bytes.extend(postcard::to_stdvec(self)?);
+		std::fs::write(binary_data_path, bytes)?;
+		Ok(())
+	}
+
+	/// Converts a model loaded from a legacy cache to the current sentinels.
+	fn replace_legacy_sentinels(&mut self) {
+		for model in self.ngrams.values_mut() {
+			model.replace_chars(|c| match c {
+				LEGACY_START_CHAR => START_CHAR,
+				LEGACY_END_CHAR => END_CHAR,
+				c => c,
+			});
+		}
+	}
+
+	/// Splits lines into chunks, builds partial models in parallel and
+	/// merges them into a final `MultiGramModel`.
+	///
+	/// The returned model is neither named nor frozen.
+	pub(crate) fn from_lines(lines: &[String]) -> Result<MultiGramModel, String> {
+		Self::from_normalized_lines(lines, &Normalization::default())
+	}
+
+	/// Builds a model like `from_lines`, normalizing every line before it is learned.
+	fn from_normalized_lines(lines: &[String], normalization: &Normalization) -> Result<MultiGramModel, String> {
 		let cpus = num_cpus::get();
 		let factor = 8;
 		let chunks = cpus * factor;
-		let chunk_size = (lines.len() + chunks - 1) / chunks;
+		let chunk_size = lines.len().div_ceil(chunks).max(1);
 
 		let (tx, rx) = mpsc::channel();
 		for chunk in lines.chunks(chunk_size) {
 			let tx = tx.clone();
 			let chunk: Vec<String> = chunk.to_vec();
+			let normalization = normalization.clone();
 
 			thread::spawn(move || {
 				let mut partial_model = MultiGramModel::default();
+				partial_model.set_normalization(&normalization);
 				for sentence in chunk {
 					partial_model.add_sentence(&sentence);
 				}
@@ -100,13 +350,11 @@ impl MultiGramModel {
 		drop(tx);
 
 		let mut final_model = MultiGramModel::default();
+		final_model.set_normalization(normalization);
 		for partial_model in rx.iter() {
 			final_model.merge(&partial_model)?;
 		}
 
-		let bytes = postcard::to_stdvec(&final_model)?;
-		std::fs::write(binary_data_path, bytes)?;
-
 		Ok(final_model)
 	}
 
@@ -115,8 +363,8 @@ impl MultiGramModel {
 	/// # Returns
 	/// - `Some(String)` if the model exists and has states.
 	/// - `None` otherwise.
-	pub fn get_random_seed(&self, n: usize) -> Option<String> {
-		self.ngrams.get(&n)?.get_random_seed()
+	pub fn get_random_seed<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Option<String> {
+		self.ngrams.get(&n)?.get_random_seed(rng)
 	}
 
 	/// Returns the number of n-gram models stored.
@@ -129,6 +377,36 @@ impl MultiGramModel {
 		&self.name
 	}
 
+	/// Sets the model's name.
+	pub(crate) fn set_name(&mut self, name: &str) {
+		self.name = name.to_owned();
+	}
+
+	/// Returns the normalization applied to learned sentences and looked-up words.
+	pub fn normalization(&self) -> &Normalization {
+		&self.normalization
+	}
+
+	/// Sets the normalization, including the case sensitivity of the n-gram
+	/// models, and rebuilds the duplicate index.
+	///
+	/// Stored sentences must already be normalized this way.
+	fn set_normalization(&mut self, normalization: &Normalization) {
+		for model in self.ngrams.values_mut() {
+			model.set_case_sensitive(normalization.case_sensitive());
+		}
+		self.normalization = normalization.clone();
+		self.index = NoveltyIndex::default();
+		for sentence in &self.sentences {
+			self.index.insert(sentence.to_lowercase());
+		}
+	}
+
+	/// Returns the casing styles of the sentences.
+	pub(crate) fn casing(&self) -> &LearnedCasing {
+		&self.casing
+	}
+
 
 
 	/// Returns the last `n` characters of a string (UTF-8 safe).
@@ -142,72 +420,354 @@ impl MultiGramModel {
 	/// Adds a sentence to the model.
 	///
 	/// # Behavior
-	/// - Prepends `START_CHAR` and appends `END_CHAR` if missing.
-	/// - Adds sentence to `self.sentences`.
-	/// - Updates all n-gram models, for n = 2 to len(sentence).
+	/// - Normalizes the sentence (see `Normalization`) and removes any sentinel
+	///   character; a non-empty sentence left empty is ignored.
+	/// - Adds the normalized sentence to `self.sentences`, the duplicate index
+	///   and the casing styles.
+	/// - Surrounds it with `START_CHAR` and `END_CHAR`.
+	/// - Updates all n-gram models, for n = 2 to len(sentence), and the unigram counts.
 	/// - Creates new `NGramModel`s if needed.
 	pub fn add_sentence(&mut self, sentence: &str) {
-		let mut s = sentence.to_string();
-		self.sentences.insert(sentence.to_owned());
-
-		if !s.starts_with(START_CHAR) {
-			s.insert(0, START_CHAR);
+		let normalized = self.normalization.apply(sentence).replace([START_CHAR, END_CHAR], "");
+		if normalized.is_empty() && !sentence.is_empty() {
+			return;
 		}
-		if !s.ends_with(END_CHAR) {
-			s.push(END_CHAR);
+		let sentence = normalized.as_str();
+		if self.sentences.insert(sentence.to_owned()) {
+			self.index.insert(sentence.to_lowercase());
+			self.casing.add(sentence);
 		}
+		let s = format!("{}{}{}", START_CHAR, sentence, END_CHAR);
 
 		for n in 2..=s.chars().count() {
-			let model = self.ngrams.entry(n).or_insert_with(|| NGramModel::new(n).unwrap());
+			let model = self.ngrams.entry(n).or_insert_with(|| {
+				let mut model = NGramModel::new(n).unwrap();
+				model.set_case_sensitive(self.normalization.case_sensitive());
+				model
+			});
 			model.add_sentence(&s);
 		}
+		// The unigram counts are the totals of the 2-gram transitions
+		if let Some(bigram) = self.ngrams.get(&2) {
+			for c in s.chars().skip(1) {
+				*self.unigram.entry(bigram.fold_char(c)).or_insert(0) += 1;
+			}
+		}
+
+		if let Some(reversed) = &mut self.reversed {
+			reversed.add_sentence(&Self::reverse(sentence));
+		}
+		self.clear_shared_views();
 	}
 
 	/// Predicts the next character for a given prefix.
 	///
 	/// # Parameters
 	/// - `key`: the prefix string
-	/// - `max_n`: maximum n-gram order to consider
-	/// - `randomness`: randomness factor (0.0..1.0)
-	/// - `reduce_random`: whether to reduce n on fallback
+	/// - `n`: n-gram order to use
+	/// - `sampling`: reshaping applied to the state's transitions
+	/// - `rng`: random source used for sampling
 	///
 	/// # Returns
 	/// - `Ok(Some(char))` if a prediction is made
 	/// - `Ok(None)` if no prediction is possible
 	/// - `Err(String)` on invalid randomness calculation
-	pub fn predict(
+	pub fn predict<R: Rng + ?Sized>(
 		&self,
 		key: &str,
-		n: usize
+		n: usize,
+		sampling: &Sampling,
+		rng: &mut R
 	) -> Result<Option<char>, String> {
 		let key_len = key.chars().count();
 		if key_len < n - 1 {
 			return Ok(None);
 		}
 		let mut next_char = None;
-		match self.ngrams.get(&n) {
-			Some(model) => {
-				let prefix = Self::last_n_chars(&key, n - 1);
-				if let Some(c) = model.predict(&prefix) {
-					next_char = Some(c);
-				}
-			},
-			None => {}
+		if let Some(model) = self.ngrams.get(&n) {
+			let prefix = Self::last_n_chars(key, n - 1);
+			if let Some(c) = model.predict(&prefix, sampling, rng) {
+				next_char = Some(c);
+			}
 		}
 
 		Ok(next_char)
 	}
 
-	/// Checks if a word already exists in the model.
+	/// Returns the transitions of the order-`n` state matching `key`, without backoff.
+	///
+	/// Transitions observed fewer than `min_count` times are ignored.
+	/// Returns `None` if the key is too short or the state does not exist.
+	pub(crate) fn distribution(&self, key: &str, n: usize, min_count: usize) -> Option<Distribution> {
+		Some(Distribution::from_counts(self.transitions(key, n)?, min_count))
+	}
+
+	/// Returns the transition counts of the order-`n` state matching `key`, without backoff.
+	///
+	/// Returns `None` if the key is too short or the state does not exist.
+	pub(crate) fn transitions(&self, key: &str, n: usize) -> Option<&BTreeMap<char, usize>> {
+		if n < 2 || key.chars().count() < n - 1 {
+			return None;
+		}
+		let state = self.ngrams.get(&n)?.state(&Self::last_n_chars(key, n - 1))?;
+		Some(state.transitions())
+	}
+
+	/// Predicts the next character for a given prefix, interpolating every
+	/// order from 2 up to `n` (see `smoothed_distribution`).
+	///
+	/// # Returns
+	/// - `Ok(Some(char))` if a prediction is made
+	/// - `Ok(None)` if the model has no learned characters
+	pub fn predict_smoothed<R: Rng + ?Sized>(
+		&self,
+		key: &str,
+		n: usize,
+		sampling: &Sampling,
+		rng: &mut R
+	) -> Result<Option<char>, String> {
+		let mut distribution = self.smoothed_distribution(key, n, sampling.min_count());
+		distribution.shape(sampling);
+		Ok(distribution.sample(rng))
+	}
+
+	/// Builds the interpolated Witten-Bell distribution of the character following `key`.
+	///
+	/// # Algorithm
+	/// - Start from the unigram distribution of the corpus.
+	/// - For each order from 2 to `n`, with context `h` (the last `order - 1` characters):
+	///   `P(c | h) = (C(h, c) + T(h) * P_lower(c)) / (C(h) + T(h))`,
+	///   where `C` are transition counts and `T(h)` the number of distinct continuations.
+	/// - Stop at the first unseen context: longer contexts contain it and are unseen too.
+	///
+	/// Transitions observed fewer than `min_count` times are ignored at every order.
+	pub(crate) fn smoothed_distribution(&self, key: &str, n: usize, min_count: usize) -> Distribution {
+		let mut probabilities = self.unigram_probabilities(min_count);
+		for order in 2..=n {
+			let Some(counts) = self.context_counts(key, order, min_count) else { break };
+
+			let total: usize = counts.iter().map(|(_, count)| count).sum();
+			let types = counts.len() as f64;
+			let denominator = total as f64 + types;
+			for probability in probabilities.values_mut() {
+				*probability *= types / denominator;
+			}
+			for (c, count) in counts {
+				*probabilities.entry(c).or_insert(0.0) += count as f64 / denominator;
+			}
+		}
+
+		Distribution::from_weights(probabilities)
+	}
+
+	/// Builds the backoff distribution of the character following `key`.
+	///
+	/// # Algorithm
+	/// - Start from the unigram distribution of the corpus.
+	/// - For each order from 2 to `n`, with context `h`: characters seen after `h`
+	///   get `(1 - UNSEEN_MASS) * C(h, c) / C(h)`, i.e. the raw counts that
+	///   `predict` samples from; the remaining `UNSEEN_MASS` is shared by the
+	///   other characters in proportion to the lower-order distribution.
+	/// - Stop at the first unseen context, as generation backs off from it.
+	///
+	/// Characters seen in the longest existing state are thus scored almost
+	/// exactly as `predict` draws them, others get a small floor, and the
+	/// result always sums to 1.
+	/// Transitions observed fewer than `min_count` times are ignored at every order.
+	pub(crate) fn backoff_distribution(&self, key: &str, n: usize, min_count: usize) -> Distribution {
+		let mut probabilities = self.unigram_probabilities(min_count);
+		for order in 2..=n {
+			let Some(counts) = self.context_counts(key, order, min_count) else { break };
+
+			let total: usize = counts.iter().map(|(_, count)| count).sum();
+			let seen: BTreeMap<char, usize> = counts.into_iter().collect();
+			let lower_seen: f64 = seen.keys().filter_map(|c| probabilities.get(c)).sum();
+			// Without unseen characters left, the state keeps the whole mass
+			let (seen_mass, unseen_factor) = if lower_seen < 1.0 - 1e-12 {
+				(1.0 - UNSEEN_MASS, UNSEEN_MASS / (1.0 - lower_seen))
+			} else {
+				(1.0, 0.0)
+			};
+			for (c, probability) in probabilities.iter_mut() {
+				if !seen.contains_key(c) {
+					*probability *= unseen_factor;
+				}
+			}
+			for (c, count) in seen {
+				probabilities.insert(c, seen_mass * count as f64 / total as f64);
+			}
+		}
+
+		Distribution::from_weights(probabilities)
+	}
+
+	/// Returns the unigram distribution of the corpus, ignoring characters
+	/// observed fewer than `min_count` times.
+	fn unigram_probabilities(&self, min_count: usize) -> BTreeMap<char, f64> {
+		let unigram_total: usize = self.unigram.values().filter(|count| **count >= min_count).sum();
+		self.unigram
+			.iter()
+			.filter(|(_, count)| **count >= min_count)
+			.map(|(c, count)| (*c, *count as f64 / unigram_total as f64))
+			.collect()
+	}
+
+	/// Returns the transitions of the order-`order` state of `key` observed at
+	/// least `min_count` times, or `None` if there are none.
+	fn context_counts(&self, key: &str, order: usize, min_count: usize) -> Option<Vec<(char, usize)>> {
+		let counts: Vec<(char, usize)> = self
+			.transitions(key, order)?
+			.iter()
+			.filter(|(_, count)| **count >= min_count)
+			.map(|(c, count)| (*c, *count))
+			.collect();
+		(!counts.is_empty()).then_some(counts)
+	}
+
+	/// Returns `true` if the character appears in the corpus (requires `freeze`).
+	///
+	/// Case-sensitive models also know `c` if they know one of its casings.
+	pub(crate) fn knows_char(&self, c: char) -> bool {
+		self.unigram.contains_key(&c)
+			|| (self.normalization.case_sensitive() && self.unigram.keys().any(|known| known.to_lowercase().eq(c.to_lowercase())))
+	}
+
+	/// Returns the log-probability of `word` under this model.
+	///
+	/// Uses the same backoff as `predict` with the whole prefix as key
+	/// (`max_n = 0`): each character is scored by the largest n-gram whose
+	/// state exists, with the raw transition counts of that state.
+	///
+	/// # Notes
+	/// - Scores differ from generation probabilities in two ways: a character
+	///   seen in the state is scored at `1 - UNSEEN_MASS` (0.999) times its
+	///   probability of being drawn, and characters never seen in the state,
+	///   which generation cannot draw from it, share a floor of `UNSEEN_MASS`
+	///   in proportion to smaller n-grams (see `backoff_distribution`).
+	/// - Characters unknown to the corpus get an add-one estimate, so the
+	///   score is always finite.
+	/// - Generation options (randomness, adaptive order, sampling filters,
+	///   constraints) are not taken into account.
+	pub fn log_prob(&self, word: &str) -> WordScore {
+		self.score(word, 0, Smoothing::Backoff, 0)
+	}
+
+	/// Returns the log-probability of `word` with explicit generation settings.
+	///
+	/// # Parameters
+	/// - `max_n`: maximum n-gram order (`0` or `1` = whole prefix)
+	/// - `smoothing`: backoff or Witten-Bell interpolation
+	/// - `min_count`: transitions observed fewer times are ignored
+	///
+	/// The word is normalized like learned sentences first.
+	pub(crate) fn score(&self, word: &str, max_n: usize, smoothing: Smoothing, min_count: usize) -> WordScore {
+		let mut key = String::from(START_CHAR);
+		let mut characters = Vec::new();
+		for c in self.normalization.fold_case(&self.normalization.apply(word)).chars() {
+			characters.push((c, self.char_probability(&key, c, max_n, smoothing, min_count).ln()));
+			key.push(c);
+		}
+		let end = self.char_probability(&key, END_CHAR, max_n, smoothing, min_count).ln();
+		WordScore::new(characters, end)
+	}
+
+	/// Returns `P(c | key)`, always strictly positive.
+	///
+	/// - `Smoothing::Backoff`: probability in the backoff distribution from the
+	///   largest existing n-gram state (at most `max_n`, see `backoff_distribution`).
+	/// - `Smoothing::WittenBell`: probability in the interpolated distribution.
+	/// - Characters absent from both: add-one estimate over the corpus characters.
+	///
+	/// Over the characters of the corpus and the end of the word, these
+	/// probabilities sum to 1 for any `key`.
+	pub(crate) fn char_probability(
+		&self,
+		key: &str,
+		c: char,
+		max_n: usize,
+		smoothing: Smoothing,
+		min_count: usize
+	) -> f64 {
+		let key_len = key.chars().count();
+		let n = if max_n < 2 { key_len + 1 } else { max_n.min(key_len + 1) };
+
+		let distribution = match smoothing {
+			Smoothing::Backoff => self.backoff_distribution(key, n, min_count),
+			Smoothing::WittenBell => self.smoothed_distribution(key, n, min_count),
+		};
+		let probability = distribution.probabilities().find(|(next_char, _)| *next_char == c).map(|(_, p)| p);
+
+		match probability {
+			Some(p) if p > 0.0 => p,
+			_ => {
+				let total: usize = self.unigram.values().sum();
+				let count = self.unigram.get(&c).copied().unwrap_or(0);
+				(count + 1) as f64 / (total + self.unigram.len() + 1) as f64
+			}
+		}
+	}
+
+	/// Defines (or redefines) a template character class for this model.
+	///
+	/// # Parameters
+	/// - `class`: class name, an uppercase ASCII letter (`C` and `V` are built in)
+	/// - `chars`: characters of the class (lowercased)
+	///
+	/// # Errors
+	/// Returns an error if `class` is not an uppercase ASCII letter or `chars` is empty.
+	pub fn set_character_class(&mut self, class: char, chars: &str) -> Result<(), String> {
+		if !class.is_ascii_uppercase() {
+			return Err(format!("Class name must be an uppercase ASCII letter, got '{}'", class));
+		}
+		if chars.is_empty() {
+			return Err(format!("Class '{}' cannot be empty", class));
+		}
+		self.classes.insert(class, chars.chars().flat_map(char::to_lowercase).collect());
+		Ok(())
+	}
+
+	/// Returns `true` if `class` is defined for this model (custom or built in).
+	pub(crate) fn has_class(&self, class: char) -> bool {
+		self.classes.contains_key(&class) || default_class(class).is_some()
+	}
+
+	/// Returns `true` if `c` belongs to `class` for this model.
+	pub(crate) fn in_class(&self, class: char, c: char) -> bool {
+		match self.classes.get(&class) {
+			Some(chars) => chars.contains(&c),
+			None => default_class(class).is_some_and(|chars| chars.contains(c)),
+		}
+	}
+
+	/// Checks if a word already exists in the model (case-insensitive).
+	///
+	/// The word is normalized like learned sentences, so equivalent spellings match.
 	pub fn check_if_exists(&self, word: &str) -> bool {
-		self.sentences.iter().any(|value| value.to_lowercase() == word.to_lowercase())
+		self.is_duplicate(word, &Novelty::default())
+	}
+
+	/// Checks if a word is a duplicate of a sentence of the model according
+	/// to `novelty` (exact or within an edit distance, with or without accents).
+	///
+	/// The word is normalized like learned sentences and compared case-insensitively.
+	pub(crate) fn is_duplicate(&self, word: &str, novelty: &Novelty) -> bool {
+		self.index.contains(&self.normalization.apply(word).to_lowercase(), novelty)
+	}
+
+	/// Returns the trie of the lowercased sentences, without accents if
+	/// `fold_accents` is set (built on first use).
+	pub(crate) fn novelty_trie(&self, fold_accents: bool) -> &Trie {
+		self.index.trie(fold_accents)
 	}
 
 	/// Merges another `MultiGramModel` into this one.
 	///
 	/// # Behavior
 	/// - Merges each n-gram model: existing models are merged in place; missing ones are cloned.
-	/// - Adds all sentences from `other` to `self.sentences`.
+	/// - Adds all sentences from `other` to `self.sentences`, the duplicate
+	///   index and the casing styles, and sums the unigram counts.
+	/// - Updates the right-to-left model, if trained.
+	/// - Drops the word-token and grapheme-cluster models, which depend on a shared table.
 	///
 	/// # Returns
 	/// - `Ok(())` on success
@@ -220,7 +780,122 @@ impl MultiGramModel {
 				self.ngrams.insert(*k, t.clone());
 			}
 		}
-		self.sentences.extend(other.sentences.clone());
+		if let Some(reversed) = &mut self.reversed {
+			match &other.reversed {
+				Some(other_reversed) => reversed.merge(other_reversed)?,
+				None => {
+					for sentence in &other.sentences {
+						reversed.add_sentence(&Self::reverse(sentence));
+					}
+				}
+			}
+		}
+		for (c, count) in &other.unigram {
+			*self.unigram.entry(*c).or_insert(0) += count;
+		}
+		for sentence in &other.sentences {
+			if self.sentences.insert(sentence.clone()) {
+				self.index.insert(sentence.to_lowercase());
+				self.casing.add(sentence);
+			}
+		}
+		self.clear_shared_views();
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::generator::Generator;
+
+	/// Builds a frozen model from `lines`.
+	fn model(lines: &[&str]) -> MultiGramModel {
+		let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+		MultiGramModel::from_lines(&lines).unwrap()
+	}
+
+	#[test]
+	fn learning_after_freeze_keeps_unigram_and_casing() {
+		let mut learned = model(&["ab", "Cd"]);
+		learned.add_sentence("Xy");
+		learned.merge(&model(&["EF", "zz"])).unwrap();
+		assert!(learned.knows_char('x') && learned.knows_char('e') && learned.knows_char('z'));
+
+		let mut frozen = learned.clone();
+		frozen.freeze();
+		assert_eq!(learned.unigram, frozen.unigram);
+		assert_eq!(learned.casing.overall_votes(), frozen.casing.overall_votes());
+	}
+
+	#[test]
+	fn char_probabilities_sum_to_one() {
+		let model = model(&["ab", "b"]);
+		let chars: Vec<char> = model.unigram.keys().copied().collect();
+		for key in ["", "a", "b", "ab", "bb", "ba"] {
+			let key = format!("{}{}", START_CHAR, key);
+			for smoothing in [Smoothing::Backoff, Smoothing::WittenBell] {
+				let sum: f64 = chars.iter().map(|c| model.char_probability(&key, *c, 0, smoothing, 0)).sum();
+				assert!((sum - 1.0).abs() < 1e-9, "{:?} after {:?}: {}", smoothing, key, sum);
+			}
+		}
+	}
+
+	#[test]
+	fn backoff_scores_follow_generation_counts() {
+		let model = model(&["ab", "ac", "b"]);
+		let score = model.log_prob("ab");
+		let seen = 1.0 - UNSEEN_MASS;
+		// 'a' in 2 of 3 words, then 'b' after "a" once in 2, then the end
+		let expected = [seen * 2.0 / 3.0, seen * 0.5];
+		for ((_, log_prob), probability) in score.characters.iter().zip(expected) {
+			assert!((log_prob.exp() - probability).abs() < 1e-12, "{} != {}", log_prob.exp(), probability);
+		}
+		assert!((score.end.exp() - seen).abs() < 1e-12);
+
+		// 'c' never follows "b": it only gets the floor
+		let unseen = model.char_probability(&format!("{}b", START_CHAR), 'c', 0, Smoothing::Backoff, 0);
+		assert!(unseen < UNSEEN_MASS, "{}", unseen);
+	}
+
+	#[test]
+	fn legacy_cache_is_converted() {
+		let directory = std::env::temp_dir().join(format!("rs-gen-legacy-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		let lines = ["marseille", "martigues", "marignane", "maurepas", "mulhouse"];
+		let data_path = directory.join("legacy.dat");
+		std::fs::write(&data_path, lines.join("\n")).unwrap();
+
+		// Cache written by earlier versions: '<' and '>' sentinels, no header
+		let mut legacy = model(&lines);
+		for ngram in legacy.ngrams.values_mut() {
+			ngram.replace_chars(|c| match c {
+				START_CHAR => LEGACY_START_CHAR,
+				END_CHAR => LEGACY_END_CHAR,
+				c => c,
+			});
+		}
+		let cache_path = build_output_path(&data_path, "bin").unwrap();
+		std::fs::write(&cache_path, postcard::to_stdvec(&legacy).unwrap()).unwrap();
+
+		let model = MultiGramModel::new(&data_path).unwrap();
+		let chars: Vec<char> = model.unigram.keys().copied().collect();
+		let rewritten = std::fs::read(&cache_path).unwrap();
+		let app = Generator::from_models(vec![model]);
+		let mut input = app.make_prediction_input();
+		input.max_n = 3;
+		let words: Vec<String> = (0..20)
+			.map(|seed| {
+				input.rng_seed = Some(seed);
+				app.predict(&input).unwrap()
+			})
+			.collect();
+		std::fs::remove_dir_all(&directory).unwrap();
+
+		assert!(rewritten.starts_with(CACHE_HEADER));
+		assert!(chars.contains(&END_CHAR) && !chars.contains(&LEGACY_END_CHAR), "{:?}", chars);
+		for word in words {
+			assert!(!word.is_empty() && !word.contains([LEGACY_START_CHAR, LEGACY_END_CHAR]), "{:?}", word);
+		}
+	}
+}
@@ -1,7 +1,9 @@
+use super::prediction_input::Sampling;
 use super::state::State;
+use rand::Rng;
 use rand::prelude::IteratorRandom;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Represents an n-gram model for sequences of characters.
 ///
@@ -24,8 +26,14 @@ pub struct NGramModel {
 	/// The order of the model (number of characters in the n-gram)
 	n: usize, // must be >= 2
 
-	/// Mapping from a prefix (length n-1) to its corresponding state
-	states: HashMap<String, State>,
+	/// Mapping from a prefix (length n-1) to its corresponding state.
+	/// Ordered by prefix so that random seed selection is reproducible.
+	states: BTreeMap<String, State>,
+
+	/// Whether characters are learned and looked up as written instead of lowercased.
+	/// Not serialized; set by the owning `MultiGramModel` (see `Normalization`).
+	#[serde(skip)]
+	case_sensitive: bool,
 }
 
 impl NGramModel {
@@ -37,15 +45,41 @@ impl NGramModel {
 		if n < 2 {
 			return Err("n must be >= 2".to_owned());
 		}
-		Ok(Self { n, states: HashMap::new() })
+		Ok(Self { n, states: BTreeMap::new(), case_sensitive: false })
+	}
+
+	/// Learns and looks up characters as written (`true`) or lowercased (`false`, default).
+	///
+	/// Must be set before learning: states already learned are not converted.
+	pub(crate) fn set_case_sensitive(&mut self, case_sensitive: bool) {
+		self.case_sensitive = case_sensitive;
+	}
+
+	/// Returns a prefix as stored in the states.
+	fn fold_prefix(&self, chars: impl Iterator<Item = char>) -> String {
+		if self.case_sensitive {
+			chars.collect()
+		} else {
+			chars.flat_map(|c| c.to_lowercase()).collect()
+		}
+	}
+
+	/// Returns a next character as stored in the states.
+	pub(crate) fn fold_char(&self, c: char) -> char {
+		if self.case_sensitive {
+			c
+		} else {
+			// Should not panic
+			c.to_lowercase().next().unwrap()
+		}
 	}
 
 	/// Returns a random prefix (seed) from the current model.
 	///
 	/// Useful for starting a generation sequence.
 	/// Returns `None` if the model has no states.
-	pub fn get_random_seed(&self) -> Option<String> {
-		self.states.keys().choose(&mut rand::rng()).cloned()
+	pub fn get_random_seed<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<String> {
+		self.states.keys().choose(rng).cloned()
 	}
 
 	/// Adds a sentence, word, or short phrase to the model.
@@ -53,7 +87,7 @@ impl NGramModel {
 	/// Breaks the input into n-grams and updates states with observed transitions.
 	///
 	/// # Notes
-	/// - Converts all characters to lowercase for consistency.
+	/// - Converts all characters to lowercase for consistency, unless case-sensitive.
 	/// - Ignores sentences shorter than `n`.
 	pub fn add_sentence(&mut self, sentence: &str) {
 		let chars: Vec<char> = sentence.chars().collect();
@@ -65,12 +99,8 @@ impl NGramModel {
 		// For each n-gram in the sentence
 		for i in 0..=chars.len() - self.n {
 			// Get the prefix and the next character
-			let prefix: String = chars[i..i + self.n - 1]
-				.iter()
-				.flat_map(|c| c.to_lowercase())
-				.collect();
-			// Should not panic
-			let next_char = chars[i + self.n - 1].to_lowercase().next().unwrap();
+			let prefix = self.fold_prefix(chars[i..i + self.n - 1].iter().copied());
+			let next_char = self.fold_char(chars[i + self.n - 1]);
 
 			// Get or create the state for this prefix
 			let state = self.states.entry(prefix.clone()).or_insert_with(|| State::new(&prefix));
@@ -78,18 +108,56 @@ impl NGramModel {
 		}
 	}
 
+	/// Freezes every state for fast sampling.
+	///
+	/// Should be called once learning is complete (after training or loading).
+	pub fn freeze(&mut self) {
+		for state in self.states.values_mut() {
+			state.freeze();
+		}
+	}
+
 	/// Predicts the next character given a prefix.
 	///
 	/// Returns `None` if the prefix is unknown or has no transitions.
 	///
 	/// # Notes
-	/// - Converts the prefix to lowercase to match stored states.
-	pub fn predict(&self, prefix: &str) -> Option<char> {
-		let key: String = prefix
-			.chars()
-			.flat_map(|c| c.to_lowercase())
+	/// - Converts the prefix to lowercase to match stored states, unless case-sensitive.
+	pub fn predict<R: Rng + ?Sized>(&self, prefix: &str, sampling: &Sampling, rng: &mut R) -> Option<char> {
+		self.state(prefix)?.predict(sampling, rng)
+	}
+
+	/// Returns the state stored for a prefix, if any.
+	///
+	/// # Notes
+	/// - Converts the prefix to lowercase to match stored states, unless case-sensitive.
+	pub(crate) fn state(&self, prefix: &str) -> Option<&State> {
+		let key = self.fold_prefix(prefix.chars());
+		self.states.get(&key)
+	}
+
+	/// Sums the transition counts of every state, per next character.
+	///
+	/// For a 2-gram model this is the unigram distribution of the corpus
+	/// (every character except `START_CHAR`, plus `END_CHAR`).
+	pub(crate) fn transition_totals(&self) -> BTreeMap<char, usize> {
+		let mut totals = BTreeMap::new();
+		for state in self.states.values() {
+			for (next_char, occurrence) in state.transitions() {
+				*totals.entry(*next_char).or_insert(0) += occurrence;
+			}
+		}
+		totals
+	}
+
+	/// Replaces characters in every state key and transition.
+	///
+	/// `map` must be injective on the learned characters. States are left unfrozen.
+	pub(crate) fn replace_chars(&mut self, map: impl Fn(char) -> char + Copy) {
+		self.states = std::mem::take(&mut self.states)
+			.into_iter()
+			.map(|(key, state)| (key.chars().map(map).collect(), state.replace_chars(map)))
 			.collect();
-		self.states.get(&key)?.predict()
 	}
 
 	/// Merges another n-gram model into this one.
@@ -0,0 +1,189 @@
+use std::collections::BTreeSet;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Removes the accents (combining marks) of a text, keeping it composed.
+pub(crate) fn strip_accents(text: &str) -> String {
+	text.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+}
+
+/// Unicode normalization form applied to text.
+///
+/// # Variants
+/// - `None`: text is kept as written (default).
+/// - `Nfc`: canonical composition ("e" + U+0301 becomes "é").
+/// - `Nfd`: canonical decomposition ("é" becomes "e" + U+0301).
+/// - `Nfkc`: compatibility composition (also maps ligatures, full-width
+///   letters, superscripts... to their plain equivalent).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NormalizationForm {
+	#[default]
+	None,
+	Nfc,
+	Nfd,
+	Nfkc,
+}
+
+/// Text normalization applied when a model learns a sentence.
+///
+/// Equivalent spellings (precomposed or decomposed accents, with or without
+/// accents) then map to the same n-grams, and the same words for the
+/// duplicate check. The generator applies it to custom seeds as well.
+///
+/// # Behavior
+/// Applied in this order:
+/// - Accents are folded, if enabled: combining marks are removed after a
+///   (compatibility, for `Nfkc`) decomposition.
+/// - The normalization form is applied.
+/// - Characters outside the allowed alphabet, if any, are removed.
+///
+/// Models are case-insensitive unless `case_sensitive` is set: characters
+/// are lowercased when learned and looked up, so "Brie" and "BRIE" share
+/// their n-grams. Learned sentences keep their casing either way (see
+/// `Casing::Learned`).
+///
+/// The default normalization leaves text unchanged and models case-insensitive.
+///
+/// # Invariants
+/// - `alphabet`, if set, is lowercase and not empty
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Normalization {
+	/// Unicode normalization form.
+	form: NormalizationForm,
+
+	/// Whether combining marks (accents) are removed.
+	fold_accents: bool,
+
+	/// Characters kept in learned text (`None` = every character).
+	alphabet: Option<BTreeSet<char>>,
+
+	/// Whether models learn characters as written instead of lowercased.
+	case_sensitive: bool,
+}
+
+impl Normalization {
+	/// Returns the normalization form.
+	pub fn form(&self) -> NormalizationForm {
+		self.form
+	}
+
+	/// Returns `true` if accents are folded.
+	pub fn fold_accents(&self) -> bool {
+		self.fold_accents
+	}
+
+	/// Returns `true` if models learn characters as written.
+	pub fn case_sensitive(&self) -> bool {
+		self.case_sensitive
+	}
+
+	/// Returns the allowed alphabet, in character order, if any.
+	pub fn alphabet(&self) -> Option<String> {
+		self.alphabet.as_ref().map(|alphabet| alphabet.iter().collect())
+	}
+
+	/// Sets the normalization form.
+	pub fn set_form(&mut self, form: NormalizationForm) {
+		self.form = form;
+	}
+
+	/// Enables or disables accent folding ("é" and "e" become the same character).
+	pub fn set_fold_accents(&mut self, fold_accents: bool) {
+		self.fold_accents = fold_accents;
+	}
+
+	/// Makes models learn characters as written (`true`) instead of lowercased (`false`, default).
+	///
+	/// Generated words then follow the casing of the corpus, e.g. "Saint" but "sur".
+	pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+		self.case_sensitive = case_sensitive;
+	}
+
+	/// Restricts learned text to the characters of `alphabet` (case-insensitive).
+	///
+	/// Other characters are removed, and sentences left empty are ignored.
+	/// Include `' '` to keep multi-word sentences. `None` keeps every character.
+	///
+	/// # Errors
+	/// Returns an error if `alphabet` is empty.
+	pub fn set_alphabet(&mut self, alphabet: Option<&str>) -> Result<(), String> {
+		self.alphabet = match alphabet {
+			Some("") => return Err("Alphabet must contain at least one character".to_owned()),
+			Some(alphabet) => Some(alphabet.chars().flat_map(char::to_lowercase).collect()),
+			None => None,
+		};
+		Ok(())
+	}
+
+	/// Returns `true` if text is left unchanged (case sensitivity aside).
+	pub fn is_identity(&self) -> bool {
+		self.form == NormalizationForm::None && !self.fold_accents && self.alphabet.is_none()
+	}
+
+	/// Returns the normalization of the views built from already normalized
+	/// sentences (reversed models): text is left unchanged, case sensitivity is kept.
+	pub(crate) fn case_only(&self) -> Normalization {
+		Normalization { case_sensitive: self.case_sensitive, ..Normalization::default() }
+	}
+
+	/// Normalizes a text.
+	pub(crate) fn apply(&self, text: &str) -> String {
+		if self.is_identity() {
+			return text.to_owned();
+		}
+
+		let folded: String = match (self.fold_accents, self.form) {
+			(false, _) => text.to_owned(),
+			(true, NormalizationForm::Nfkc) => text.nfkd().filter(|c| !is_combining_mark(*c)).collect(),
+			(true, _) => text.nfd().filter(|c| !is_combining_mark(*c)).collect(),
+		};
+		let normalized: String = match self.form {
+			NormalizationForm::None => folded,
+			NormalizationForm::Nfc => folded.nfc().collect(),
+			NormalizationForm::Nfd => folded.nfd().collect(),
+			NormalizationForm::Nfkc => folded.nfkc().collect(),
+		};
+		match &self.alphabet {
+			Some(alphabet) => normalized
+				.chars()
+				.filter(|c| c.to_lowercase().all(|lower| alphabet.contains(&lower)))
+				.collect(),
+			None => normalized,
+		}
+	}
+
+	/// Lowercases a normalized text, unless models are case-sensitive,
+	/// so that it can be compared to learned transitions.
+	pub(crate) fn fold_case(&self, text: &str) -> String {
+		if self.case_sensitive { text.to_owned() } else { text.to_lowercase() }
+	}
+
+	/// Returns a short tag identifying this normalization, used to name
+	/// binary caches, or `None` for the default normalization.
+	///
+	/// The alphabet is identified by a 64-bit FNV-1a hash of its characters.
+	pub(crate) fn cache_tag(&self) -> Option<String> {
+		let mut parts = Vec::new();
+		match self.form {
+			NormalizationForm::None => {}
+			NormalizationForm::Nfc => parts.push("nfc".to_owned()),
+			NormalizationForm::Nfd => parts.push("nfd".to_owned()),
+			NormalizationForm::Nfkc => parts.push("nfkc".to_owned()),
+		}
+		if self.fold_accents {
+			parts.push("fold".to_owned());
+		}
+		if let Some(alphabet) = &self.alphabet {
+			let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+			for byte in alphabet.iter().collect::<String>().bytes() {
+				hash ^= byte as u64;
+				hash = hash.wrapping_mul(0x0100_0000_01b3);
+			}
+			parts.push(format!("a{:016x}", hash));
+		}
+		if self.case_sensitive {
+			parts.push("cased".to_owned());
+		}
+		if parts.is_empty() { None } else { Some(parts.join("-")) }
+	}
+}
@@ -0,0 +1,301 @@
+use super::normalization::strip_accents;
+use crate::text::edit_distance_chars;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// How close to a training word a generated word may be.
+///
+/// By default, only exact copies of a training word (ignoring case) are
+/// duplicates. Accents can be ignored as well, and a positive `distance`
+/// also rejects words within that many edits (insertions, deletions or
+/// substitutions) of a training word, e.g. "pikachi" for "pikachu".
+///
+/// Duplicates are retried like other rejected words, up to `nb_try` times.
+/// With `during_generation`, exact copies are avoided while sampling instead,
+/// so that no retry is needed for them.
+///
+/// `pairwise_distance` applies to the words of a batch among themselves
+/// (see `Generator::predict_many`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Novelty {
+	/// Whether "é" and "e" are the same letter when comparing words.
+	fold_accents: bool,
+
+	/// Words within this edit distance of a training word are duplicates (`0` = exact copies only).
+	distance: usize,
+
+	/// Whether the end of the word is masked when it would complete a training word.
+	during_generation: bool,
+
+	/// Words of a unique batch within this edit distance of each other are duplicates (`0` = identical words only).
+	pairwise_distance: usize,
+}
+
+impl Novelty {
+	/// Returns `true` if accents are ignored when comparing words.
+	pub fn fold_accents(&self) -> bool {
+		self.fold_accents
+	}
+
+	/// Returns the edit distance within which words are duplicates.
+	pub fn distance(&self) -> usize {
+		self.distance
+	}
+
+	/// Ignores accents (`true`) or not (`false`, default) when comparing words.
+	pub fn set_fold_accents(&mut self, fold_accents: bool) {
+		self.fold_accents = fold_accents;
+	}
+
+	/// Rejects words within `distance` edits of a training word.
+	///
+	/// `0` (default) only rejects exact copies. Each edit is one character
+	/// inserted, deleted or substituted.
+	pub fn set_distance(&mut self, distance: usize) {
+		self.distance = distance;
+	}
+
+	/// Returns `true` if exact copies are avoided while sampling.
+	pub fn during_generation(&self) -> bool {
+		self.during_generation
+	}
+
+	/// Avoids exact copies of training words while sampling (`true`) or only
+	/// by retrying complete words (`false`, default).
+	///
+	/// While the word being generated is still the beginning of a training
+	/// word, it is followed in a trie of the training words. When the word is
+	/// itself a training word, the end of the word is masked; if the end was
+	/// the only continuation left, the generator backs off to a shorter n-gram.
+	/// Exact copies are then only possible if no shorter n-gram can continue
+	/// the word, and they are still retried.
+	///
+	/// # Notes
+	/// - Only applies to forward generation of characters; words generated
+	///   from an end seed, tokens and grapheme clusters are retried as before.
+	/// - Near-duplicates (see `set_distance`) are still retried.
+	pub fn set_during_generation(&mut self, during_generation: bool) {
+		self.during_generation = during_generation;
+	}
+
+	/// Returns the edit distance within which words of a unique batch are duplicates.
+	pub fn pairwise_distance(&self) -> usize {
+		self.pairwise_distance
+	}
+
+	/// Rejects words of a unique batch within `distance` edits of an earlier
+	/// word of the batch.
+	///
+	/// `0` (default) only rejects identical words (case-insensitive).
+	/// Independent of `set_distance`, which compares words with the training words.
+	pub fn set_pairwise_distance(&mut self, distance: usize) {
+		self.pairwise_distance = distance;
+	}
+}
+
+/// Lookup index of the training words of a model, for the duplicate check.
+///
+/// # Responsibilities
+/// - Answer exact lookups in constant time, with or without accents
+/// - Answer "within k edits" lookups with a BK-tree, built on first use
+/// - Follow words being generated with a trie, built on first use
+///
+/// # Invariants
+/// - Keys are normalized and lowercased by the owning model
+/// - `folded` holds the keys of `exact` without accents
+/// - `trees[0]` and `tries[0]` index `exact`, `trees[1]` and `tries[1]` index
+///   `folded`; all are cleared whenever a key is inserted
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NoveltyIndex {
+	exact: HashSet<String>,
+	folded: HashSet<String>,
+	trees: [OnceLock<BkTree>; 2],
+	tries: [OnceLock<Trie>; 2],
+}
+
+impl NoveltyIndex {
+	/// Adds a key (a normalized, lowercased training word).
+	pub(crate) fn insert(&mut self, key: String) {
+		self.folded.insert(strip_accents(&key));
+		self.exact.insert(key);
+		self.trees = Default::default();
+		self.tries = Default::default();
+	}
+
+	/// Returns the trie of the keys, without accents if `fold_accents` is set.
+	pub(crate) fn trie(&self, fold_accents: bool) -> &Trie {
+		if fold_accents {
+			self.tries[1].get_or_init(|| Trie::build(&self.folded))
+		} else {
+			self.tries[0].get_or_init(|| Trie::build(&self.exact))
+		}
+	}
+
+	/// Returns `true` if a key (normalized and lowercased like the inserted
+	/// ones) is a duplicate according to `novelty`.
+	pub(crate) fn contains(&self, key: &str, novelty: &Novelty) -> bool {
+		let (keys, tree) = if novelty.fold_accents {
+			(&self.folded, &self.trees[1])
+		} else {
+			(&self.exact, &self.trees[0])
+		};
+		let key = if novelty.fold_accents { strip_accents(key) } else { key.to_owned() };
+		if keys.contains(&key) {
+			return true;
+		}
+		if novelty.distance == 0 {
+			return false;
+		}
+		let key: Vec<char> = key.chars().collect();
+		tree.get_or_init(|| BkTree::build(keys)).has_within(&key, novelty.distance)
+	}
+}
+
+/// Burkhard-Keller tree over the Levenshtein distance.
+///
+/// Each child is keyed by its distance to its parent; by the triangle
+/// inequality, a search within `k` of a query at distance `d` from a node
+/// only visits the children keyed `d - k` to `d + k`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BkTree {
+	/// Nodes as `(word, children by distance)`; the root is the first node.
+	nodes: Vec<(Vec<char>, BTreeMap<usize, usize>)>,
+}
+
+impl BkTree {
+	/// Builds a tree over `words`, inserted in sorted order for reproducible shapes.
+	fn build(words: &HashSet<String>) -> Self {
+		let mut sorted: Vec<&String> = words.iter().collect();
+		sorted.sort();
+		let mut tree = Self::default();
+		for word in sorted {
+			tree.insert(word.chars().collect());
+		}
+		tree
+	}
+
+	/// Inserts a word (ignored if already present).
+	pub(crate) fn insert(&mut self, word: Vec<char>) {
+		if self.nodes.is_empty() {
+			self.nodes.push((word, BTreeMap::new()));
+			return;
+		}
+		let mut index = 0;
+		loop {
+			let distance = edit_distance_chars(&word, &self.nodes[index].0, usize::MAX).unwrap_or(usize::MAX);
+			if distance == 0 {
+				return;
+			}
+			match self.nodes[index].1.get(&distance) {
+				Some(child) => index = *child,
+				None => {
+					let child = self.nodes.len();
+					self.nodes.push((word, BTreeMap::new()));
+					self.nodes[index].1.insert(distance, child);
+					return;
+				}
+			}
+		}
+	}
+
+	/// Returns `true` if a word of the tree is within `limit` edits of `word`.
+	///
+	/// Distances to a node are only computed up to its farthest child plus
+	/// `limit`: beyond, neither the node nor its children can match.
+	pub(crate) fn has_within(&self, word: &[char], limit: usize) -> bool {
+		let mut pending = if self.nodes.is_empty() { vec![] } else { vec![0] };
+		while let Some(index) = pending.pop() {
+			let (node, children) = &self.nodes[index];
+			let bound = children.keys().next_back().map_or(limit, |farthest| farthest.saturating_add(limit));
+			let Some(distance) = edit_distance_chars(word, node, bound) else { continue };
+			if distance <= limit {
+				return true;
+			}
+			let range = distance.saturating_sub(limit)..=distance.saturating_add(limit);
+			pending.extend(children.range(range).map(|(_, child)| *child));
+		}
+		false
+	}
+}
+
+/// Prefix tree of the training words, for the duplicate check during generation.
+///
+/// Nodes are numbered from the root (`0`); edges are stored in a single map
+/// rather than per node, since most nodes have a single child.
+#[derive(Clone, Debug)]
+pub(crate) struct Trie {
+	/// Child of a node for a character.
+	edges: HashMap<(usize, char), usize>,
+	/// Whether the path to each node spells a whole word.
+	terminal: Vec<bool>,
+}
+
+impl Trie {
+	/// Builds a trie over `words`.
+	fn build(words: &HashSet<String>) -> Self {
+		let mut trie = Self { edges: HashMap::new(), terminal: vec![false] };
+		for word in words {
+			let mut node = 0;
+			for c in word.chars() {
+				let next = trie.terminal.len();
+				node = *trie.edges.entry((node, c)).or_insert(next);
+				if node == next {
+					trie.terminal.push(false);
+				}
+			}
+			trie.terminal[node] = true;
+		}
+		trie
+	}
+}
+
+/// Positions of a word being generated in the tries of several models.
+///
+/// # Invariants
+/// - Only tries in which the word is still the beginning of a training word are kept
+/// - Characters are lowercased, and stripped of accents with `fold_accents`,
+///   like the keys of the tries
+#[derive(Clone, Debug)]
+pub(crate) struct TriePaths<'a> {
+	fold_accents: bool,
+	paths: Vec<(&'a Trie, usize)>,
+}
+
+impl<'a> TriePaths<'a> {
+	/// Starts at the root of every trie, then follows `prefix`.
+	pub(crate) fn new(tries: impl Iterator<Item = &'a Trie>, fold_accents: bool, prefix: &str) -> Self {
+		let mut paths = Self { fold_accents, paths: tries.map(|trie| (trie, 0)).collect() };
+		for c in prefix.chars() {
+			paths.push(c);
+		}
+		paths
+	}
+
+	/// Follows a generated character; tries without a matching edge are dropped.
+	pub(crate) fn push(&mut self, c: char) {
+		if self.paths.is_empty() {
+			return;
+		}
+		let lowercase: String = c.to_lowercase().collect();
+		let key = if self.fold_accents { strip_accents(&lowercase) } else { lowercase };
+		self.paths.retain_mut(|(trie, node)| {
+			for c in key.chars() {
+				match trie.edges.get(&(*node, c)) {
+					Some(next) => *node = *next,
+					None => return false,
+				}
+			}
+			true
+		});
+	}
+
+	/// Returns `true` if the word followed so far is not the beginning of any training word.
+	pub(crate) fn is_empty(&self) -> bool {
+		self.paths.is_empty()
+	}
+
+	/// Returns `true` if the word followed so far is a training word of one of the tries.
+	pub(crate) fn is_word(&self) -> bool {
+		self.paths.iter().any(|(trie, node)| trie.terminal[*node])
+	}
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use super::constraints::{CONSTRAINED_MAX_LEN, Constraints};
+use super::novelty::Novelty;
+use super::template::Template;
+use std::collections::BTreeMap;
 
 /// Strategy used to select the starting seed when generating a sequence.
 ///
@@ -17,6 +20,163 @@ pub enum StartSeed {
 	False,
 }
 
+/// Strategy used to select the ending of the word, for backward generation.
+///
+/// When set alone, the word is generated right to left from its ending, using
+/// the reversed models of `MultiGramModel::train_reversed`. Combined with a
+/// start seed, the middle part is generated between both (infill); reversed
+/// models are then only needed by `Random`.
+///
+/// # Variants
+/// - `Random(usize)`: select a random ending, using up to the given
+///   n-gram size as a bound.
+/// - `Custom(&str)`: use the provided string as the ending.
+/// - `False`: no end seed; generation runs left to right.
+#[derive(PartialEq)]
+pub enum EndSeed {
+	Random(usize),
+	Custom(String),
+	False,
+}
+
+/// Method used to build the next-character distribution from the n-gram orders.
+///
+/// # Variants
+/// - `Backoff`: use the requested order only; if its key is missing, the
+///   generator drops to the next smaller order (default).
+/// - `WittenBell`: interpolate every available order, from 2 up to the
+///   requested one, with Witten-Bell weights. Each order contributes
+///   according to how many distinct continuations its context has seen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Smoothing {
+	#[default]
+	Backoff,
+	WittenBell,
+}
+
+/// How the weighted models are combined when drawing each character.
+///
+/// # Variants
+/// - `PerCharacter`: pick one model per character according to the normalized
+///   intensities, then sample from that model only (default).
+/// - `Mixture`: combine the next-character distributions of every weighted
+///   model into one mixture, weighted by the normalized intensities, and
+///   sample from it. Models without the current key are left out and the
+///   remaining weights are renormalized.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Blending {
+	#[default]
+	PerCharacter,
+	Mixture,
+}
+
+/// Unit of the n-grams used to generate a word.
+///
+/// # Variants
+/// - `Characters`: characters follow characters (default).
+/// - `Words`: whitespace-separated tokens follow tokens, so that multi-word
+///   structures (e.g. "X sur Y") stay well-formed. Requires
+///   `Generator::train_tokens`.
+/// - `Graphemes`: user-perceived characters (grapheme clusters) follow each
+///   other, so that a letter with combining accents or an emoji sequence
+///   such as a flag is never split. Lengths are counted in clusters.
+///   Requires `Generator::train_graphemes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Tokenization {
+	#[default]
+	Characters,
+	Words,
+	Graphemes,
+}
+
+/// Casing of the generated words.
+///
+/// Applied to each part of the word separated by whitespace or hyphens, so
+/// that multi-word names are cased part by part.
+///
+/// # Variants
+/// - `Unchanged`: words are returned as generated, lowercase unless models
+///   are case-sensitive (default).
+/// - `Learned`: each part takes the majority casing of the weighted models'
+///   corpora (weighted by their intensities): the casing of that part if it
+///   appears in a corpus (e.g. "sur"), otherwise the dominant casing of the corpora.
+/// - `Title`: "Saint-Denis Sur Mer".
+/// - `Upper`: "SAINT-DENIS SUR MER".
+/// - `Lower`: "saint-denis sur mer".
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Casing {
+	#[default]
+	Unchanged,
+	Learned,
+	Title,
+	Upper,
+	Lower,
+}
+
+/// Parameters reshaping a state's transitions before the next character is drawn.
+///
+/// Applied independently to every state visited during generation.
+///
+/// Filters are applied in this order: `min_count`, `temperature`, `top_k`, `top_p`.
+///
+/// # Invariants
+/// - `temperature` is finite and strictly positive
+/// - `top_k`, if set, is at least 1
+/// - `top_p`, if set, is in `(0.0, 1.0]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sampling {
+	/// Exponent applied to transition counts as `count^(1 / temperature)`.
+	/// `1.0` keeps the learned distribution unchanged.
+	temperature: f32,
+
+	/// Keep only the `k` most likely transitions of a state.
+	top_k: Option<usize>,
+
+	/// Keep only the most likely transitions whose cumulative probability reaches `p`.
+	top_p: Option<f32>,
+
+	/// Ignore transitions observed fewer than `min_count` times.
+	min_count: usize,
+}
+
+impl Default for Sampling {
+	fn default() -> Self {
+		Self {
+			temperature: 1.0,
+			top_k: None,
+			top_p: None,
+			min_count: 0,
+		}
+	}
+}
+
+impl Sampling {
+	/// Returns the sampling temperature.
+	pub fn temperature(&self) -> f32 {
+		self.temperature
+	}
+
+	/// Returns the top-k limit, if any.
+	pub fn top_k(&self) -> Option<usize> {
+		self.top_k
+	}
+
+	/// Returns the top-p (nucleus) threshold, if any.
+	pub fn top_p(&self) -> Option<f32> {
+		self.top_p
+	}
+
+	/// Returns the minimum transition count.
+	pub fn min_count(&self) -> usize {
+		self.min_count
+	}
+
+	/// Returns `true` if sampling leaves the learned distribution unchanged.
+	pub fn is_identity(&self) -> bool {
+		self.temperature == 1.0 && self.top_k.is_none() && self.top_p.is_none() && self.min_count <= 1
+	}
+}
+
 /// Input parameters for generating predictions from multiple models.
 ///
 /// `PredictionInput` contains both **configuration parameters** (like randomness,
@@ -24,13 +184,20 @@ pub enum StartSeed {
 /// normalized probabilities).
 ///
 /// # Responsibilities
-/// - Track generation parameters (`max_n`, `nb_try`, `randomness`, `reduce_random`, `start_seed`)
+/// - Track generation parameters (`max_n`, `nb_try`, `randomness`, `reduce_random`, `adaptive_order`, `start_seed`, `end_seed`, `rng_seed`, `smoothing`, `blending`)
+/// - Track per-state sampling parameters (`temperature`, `top_k`, `top_p`, `min_count`)
+/// - Track length constraints (`min_len`, `max_len`, `end_scaling`)
+/// - Track word constraints (`constraints`, `template`)
+/// - Track the duplicate check (`novelty`)
+/// - Track the n-gram unit (`tokenization`, `novel_tokens`)
+/// - Track the output casing (`casing`)
 /// - Track per-model intensity (user-adjustable) and compute normalized selection probabilities
 /// - Ensure probabilities always sum to 1.0 (if there are any models)
 ///
 /// # Invariants
 /// - `models_intensity` keys are fixed and correspond to available models
 /// - `models_probability` is always normalized or uniform if all intensities are zero
+/// - `min_len <= max_len` when `max_len` is set
 pub struct PredictionInput {
 	/// Maximum n-gram size to consider in predictions.
 	pub max_n: usize,
@@ -44,32 +211,97 @@ pub struct PredictionInput {
 	/// Whether to reduce n when falling back during a generation.
 	pub reduce_random: bool,
 
+	/// Minimum number of observations of a state for its order to be kept
+	/// (`None` = the order is not adapted to the branching of states).
+	adaptive_order: Option<usize>,
+
+	/// How the orders of a model are combined into a next-character distribution.
+	pub smoothing: Smoothing,
+
+	/// How the weighted models are combined when drawing each character.
+	pub blending: Blending,
+
+	/// Per-state sampling parameters (temperature, top-k, top-p, minimum count).
+	sampling: Sampling,
+
+	/// Minimum word length in characters (`0` = no minimum).
+	min_len: usize,
+
+	/// Maximum word length in characters (`None` = unbounded).
+	max_len: Option<usize>,
+
+	/// Strength of the position-dependent scaling of the end transition (`0.0` = disabled).
+	end_scaling: f32,
+
+	/// Constraints on the generated word (suffix, substrings, regular expression).
+	pub constraints: Constraints,
+
+	/// Optional skeleton (e.g. `CVCCV`) the generated word must follow.
+	pub template: Option<Template>,
+
+	/// How close to a training word a generated word may be (exact copies by default).
+	pub novelty: Novelty,
+
+	/// Unit of the n-grams: characters, whitespace-separated tokens or grapheme clusters.
+	pub tokenization: Tokenization,
+
+	/// With `Tokenization::Words`, probability of spelling a new token
+	/// character by character where the models expect a rare one.
+	novel_tokens: f32,
+
+	/// Casing of the generated words.
+	pub casing: Casing,
+
 	/// Optional starting seed for generation.
 	pub start_seed: StartSeed,
 
+	/// Optional ending of the word; when set, the word is generated backward.
+	pub end_seed: EndSeed,
+
+	/// Optional seed for the random number generator.
+	///
+	/// When set, `Generator::predict` produces the same word for the same
+	/// models and parameters on every run. When `None`, a fresh seed is drawn.
+	pub rng_seed: Option<u64>,
+
 	/// User-adjustable model intensities (not normalized).
-	models_intensity: HashMap<String, f32>,
+	models_intensity: BTreeMap<String, f32>,
 
 	/// Normalized probabilities derived from `models_intensity`.
-	models_probability: HashMap<String, f32>,
+	models_probability: BTreeMap<String, f32>,
 }
 
-impl<'a> PredictionInput {
+impl PredictionInput {
 	/// Creates a new `PredictionInput` from a set of model intensities.
 	///
 	/// Normalizes the probabilities internally.
 	///
 	/// # Visibility
 	/// - `pub(crate)` to prevent construction outside the crate.
-	pub(crate) fn new(models_intensity: HashMap<String, f32>) -> Self {
+	pub(crate) fn new(models_intensity: BTreeMap<String, f32>) -> Self {
 		let mut prediction_input = Self {
 			max_n: 0,
 			nb_try: 0,
 			randomness: 0.0,
 			reduce_random: false,
+			adaptive_order: None,
+			smoothing: Smoothing::default(),
+			blending: Blending::default(),
+			sampling: Sampling::default(),
+			min_len: 0,
+			max_len: None,
+			end_scaling: 0.0,
+			constraints: Constraints::default(),
+			template: None,
+			novelty: Novelty::default(),
+			tokenization: Tokenization::default(),
+			novel_tokens: 0.0,
+			casing: Casing::default(),
 			start_seed: StartSeed::False,
+			end_seed: EndSeed::False,
+			rng_seed: None,
 			models_intensity,
-			models_probability: HashMap::new(),
+			models_probability: BTreeMap::new(),
 		};
 
 		prediction_input.normalize();
@@ -105,9 +337,86 @@ impl<'a> PredictionInput {
 		self.randomness
 	}
 
+	/// Returns the sampling temperature.
+	pub fn temperature(&self) -> f32 {
+		self.sampling.temperature
+	}
+
+	/// Returns the minimum number of observations of a state for its order to
+	/// be kept, if the order adapts to the branching of states.
+	pub fn adaptive_order(&self) -> Option<usize> {
+		self.adaptive_order
+	}
+
+	/// Returns the per-state sampling parameters.
+	pub fn sampling(&self) -> &Sampling {
+		&self.sampling
+	}
+
+	/// Returns the minimum word length in characters.
+	pub fn min_len(&self) -> usize {
+		self.min_len
+	}
+
+	/// Returns the maximum word length in characters, if any.
+	pub fn max_len(&self) -> Option<usize> {
+		self.max_len
+	}
+
+	/// Returns the strength of the end transition scaling.
+	pub fn end_scaling(&self) -> f32 {
+		self.end_scaling
+	}
+
+	/// Returns the probability of spelling a new token where a rare one is expected.
+	pub fn novel_tokens(&self) -> f32 {
+		self.novel_tokens
+	}
+
+	/// Returns the factor applied to the end transition of a word of `len` characters.
+	///
+	/// `exp(strength * (len - target) / spread)`, where `target` is the midpoint
+	/// of `[min_len, max_len]` and `spread` half its width (or `min_len` and half
+	/// of it if there is no maximum). Returns `None` if scaling is disabled or
+	/// no bound is set.
+	pub(crate) fn end_scaling_factor(&self, len: usize) -> Option<f64> {
+		if self.end_scaling <= 0.0 {
+			return None;
+		}
+		let (target, spread) = match self.max_len {
+			Some(max_len) => ((self.min_len + max_len) as f64 / 2.0, (max_len - self.min_len) as f64 / 2.0),
+			None if self.min_len > 0 => (self.min_len as f64, self.min_len as f64 / 2.0),
+			None => return None,
+		};
+		Some((self.end_scaling as f64 * (len as f64 - target) / spread.max(1.0)).exp())
+	}
+
+	/// Returns the length at which only the end of the word can be drawn.
+	///
+	/// `max_len` if set; otherwise a fixed cap when constraints are active,
+	/// so that a constrained word cannot grow forever.
+	pub(crate) fn effective_max_len(&self) -> Option<usize> {
+		match self.max_len {
+			Some(max_len) => Some(max_len),
+			None if self.constraints.is_active() => Some(CONSTRAINED_MAX_LEN.max(self.min_len)),
+			None => None,
+		}
+	}
+
+	/// Returns `true` if a word of `len` characters satisfies the length bounds.
+	pub(crate) fn accepts_length(&self, len: usize) -> bool {
+		len >= self.min_len && self.max_len.is_none_or(|max_len| len <= max_len)
+	}
+
+	/// Returns `true` if the candidate characters must be filtered at each step
+	/// (the fast sampling path cannot be used).
+	pub(crate) fn has_step_constraints(&self) -> bool {
+		self.min_len > 0 || self.max_len.is_some() || self.constraints.is_active() || self.template.is_some()
+	}
+
 	/// Returns an iterator over normalized model probabilities.
 	///
-	/// Each item is a tuple `(model_name, probability)`.
+	/// Each item is a tuple `(model_name, probability)`, ordered by model name.
 	pub fn models_probability(&self) -> impl Iterator<Item=(&str, f32)> {
 		self.models_probability.iter().map(|(k, v)| (k.as_str(), *v))
 	}
@@ -124,6 +433,138 @@ impl<'a> PredictionInput {
 		Ok(())
 	}
 
+	/// Sets the sampling temperature applied to every state's transitions.
+	///
+	/// - `temperature < 1.0`: more conservative, closer to the corpus
+	/// - `temperature = 1.0`: learned distribution (default)
+	/// - `temperature > 1.0`: more adventurous, rare transitions are favored
+	///
+	/// # Errors
+	/// Returns an error if the value is not strictly positive and finite.
+	pub fn set_temperature(&mut self, temperature: f32) -> Result<(), String> {
+		if !temperature.is_finite() || temperature <= 0.0 {
+			return Err("Temperature must be a finite value greater than 0.0".to_owned());
+		}
+		self.sampling.temperature = temperature;
+		Ok(())
+	}
+
+	/// Keeps only the `k` most likely transitions of each state.
+	///
+	/// `None` disables the filter.
+	///
+	/// # Errors
+	/// Returns an error if `k` is 0.
+	pub fn set_top_k(&mut self, top_k: Option<usize>) -> Result<(), String> {
+		if top_k == Some(0) {
+			return Err("Top-k must be at least 1".to_owned());
+		}
+		self.sampling.top_k = top_k;
+		Ok(())
+	}
+
+	/// Keeps only the most likely transitions of each state whose cumulative
+	/// probability reaches `p` (nucleus sampling).
+	///
+	/// `None` disables the filter.
+	///
+	/// # Errors
+	/// Returns an error if `p` is not in `(0.0, 1.0]`.
+	pub fn set_top_p(&mut self, top_p: Option<f32>) -> Result<(), String> {
+		if let Some(p) = top_p
+			&& !(p > 0.0 && p <= 1.0)
+		{
+			return Err("Top-p must be greater than 0.0 and at most 1.0".to_owned());
+		}
+		self.sampling.top_p = top_p;
+		Ok(())
+	}
+
+	/// Ignores transitions observed fewer than `min_count` times.
+	///
+	/// `0` or `1` disables the filter. If every transition of a state is
+	/// filtered out, generation backs off to a smaller n-gram as if the key
+	/// did not exist.
+	pub fn set_min_count(&mut self, min_count: usize) {
+		self.sampling.min_count = min_count;
+	}
+
+	/// Adapts the n-gram size to the branching of states.
+	///
+	/// With `Some(min_continuations)`, the n-gram size chosen for each character
+	/// is lowered while the state at that size has a single outgoing transition,
+	/// or was observed fewer than `min_continuations` times. Long contexts are
+	/// kept where the corpus offers a choice, so words stay fluent, while long
+	/// training substrings, which `max_n = 0` would otherwise copy verbatim,
+	/// are left at their first fork.
+	///
+	/// `None` (default) keeps the size chosen from `max_n` and `randomness`.
+	/// Transitions ignored by `min_count` are not counted.
+	pub fn set_adaptive_order(&mut self, min_continuations: Option<usize>) {
+		self.adaptive_order = min_continuations;
+	}
+
+	/// Bounds the length of generated words, in characters (seed included).
+	///
+	/// - Below `min_len`, the end of the word cannot be drawn.
+	/// - At `max_len`, only the end of the word can be drawn.
+	///
+	/// Words that still fall outside the bounds (dead ends) are retried,
+	/// like duplicates, up to `nb_try` times.
+	///
+	/// # Errors
+	/// Returns an error if `max_len` is 0 or smaller than `min_len`.
+	pub fn set_length_bounds(&mut self, min_len: usize, max_len: Option<usize>) -> Result<(), String> {
+		if let Some(max_len) = max_len {
+			if max_len == 0 {
+				return Err("Maximum length must be at least 1".to_owned());
+			}
+			if max_len < min_len {
+				return Err("Maximum length must be greater than or equal to minimum length".to_owned());
+			}
+		}
+		self.min_len = min_len;
+		self.max_len = max_len;
+		Ok(())
+	}
+
+	/// Scales the probability of ending the word according to its length.
+	///
+	/// The end transition weight is multiplied by
+	/// `exp(strength * (len - target) / spread)`, where `target` is the midpoint
+	/// of the length bounds and `spread` half their width (or `min_len` and half
+	/// of it if there is no maximum). Short words are then unlikely to end early
+	/// and long ones are pushed to finish. Has no effect without length bounds.
+	///
+	/// `0.0` disables the scaling.
+	///
+	/// # Errors
+	/// Returns an error if the value is negative or not finite.
+	pub fn set_end_scaling(&mut self, strength: f32) -> Result<(), String> {
+		if !strength.is_finite() || strength < 0.0 {
+			return Err("End scaling must be a finite value greater than or equal to 0.0".to_owned());
+		}
+		self.end_scaling = strength;
+		Ok(())
+	}
+
+	/// Sets the probability of spelling a new token where a rare one is expected.
+	///
+	/// With `Tokenization::Words`, tokens seen once in a corpus (mostly proper
+	/// names) are learned as a single "rare token". Where it is generated, a
+	/// rare token of the corpus is copied, or, with this probability, a new
+	/// one is spelled by a character model trained on the rare tokens.
+	///
+	/// # Errors
+	/// Returns an error if the probability is outside [0.0, 1.0].
+	pub fn set_novel_tokens(&mut self, probability: f32) -> Result<(), String> {
+		if !(0.0..=1.0).contains(&probability) {
+			return Err(format!("Novel tokens probability must be between 0.0 and 1.0, got {}", probability));
+		}
+		self.novel_tokens = probability;
+		Ok(())
+	}
+
 	/// Sets the intensity of a specific model.
 	///
 	/// Automatically normalizes probabilities after adjustment.
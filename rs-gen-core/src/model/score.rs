@@ -0,0 +1,48 @@
+/// Log-probability of a word under a model.
+///
+/// All values are natural logarithms. The probability of each character is
+/// conditioned on the characters before it, starting from the beginning of
+/// the word; the probability of ending the word after its last character is
+/// kept separately.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WordScore {
+	/// Each character of the word with its log-probability.
+	pub characters: Vec<(char, f64)>,
+
+	/// Log-probability of the word ending after its last character.
+	pub end: f64,
+
+	/// Sum of every character log-probability and `end`.
+	pub total: f64,
+}
+
+impl WordScore {
+	/// Builds a score from per-character log-probabilities and the end log-probability.
+	pub(crate) fn new(characters: Vec<(char, f64)>, end: f64) -> Self {
+		let total = characters.iter().map(|(_, log_prob)| log_prob).sum::<f64>() + end;
+		Self { characters, end, total }
+	}
+
+	/// Number of predicted symbols: every character plus the end of the word.
+	pub fn len(&self) -> usize {
+		self.characters.len() + 1
+	}
+
+	/// Always `false`: the end of the word is always scored.
+	pub fn is_empty(&self) -> bool {
+		false
+	}
+
+	/// Average log-probability per predicted symbol.
+	pub fn average(&self) -> f64 {
+		self.total / self.len() as f64
+	}
+
+	/// Perplexity of the word: `exp(-average)`.
+	///
+	/// Lower is better; a perplexity of `k` means the model was, on average,
+	/// as uncertain as a uniform choice between `k` characters.
+	pub fn perplexity(&self) -> f64 {
+		(-self.average()).exp()
+	}
+}
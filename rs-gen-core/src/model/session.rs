@@ -0,0 +1,144 @@
+use crate::io::read_file;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// Words already produced across several generations.
+///
+/// `Generator::predict_in_session` treats the words of a session as
+/// duplicates, like training words, and records each word it returns, so
+/// that repeated calls (e.g. a user clicking "Generate") do not bring back
+/// the same invented word.
+///
+/// # Responsibilities
+/// - Remember produced words, in the order they were produced
+/// - Forget the oldest words beyond an optional cap
+/// - Save to and load from a text file (one word per line, oldest first)
+///
+/// # Invariants
+/// - `keys` holds the lowercased words of `words`
+/// - `words.len() <= cap` when a cap is set
+#[derive(Clone, Debug, Default)]
+pub struct GenerationSession {
+	/// Produced words, oldest first.
+	words: VecDeque<String>,
+
+	/// Lowercased words, for case-insensitive lookups.
+	keys: HashSet<String>,
+
+	/// Maximum number of remembered words (`None` = unbounded).
+	cap: Option<usize>,
+}
+
+impl GenerationSession {
+	/// Creates an empty session remembering at most `cap` words (`None` = unbounded).
+	///
+	/// # Errors
+	/// Returns an error if `cap` is `Some(0)`.
+	pub fn new(cap: Option<usize>) -> Result<Self, String> {
+		let mut session = Self::default();
+		session.set_cap(cap)?;
+		Ok(session)
+	}
+
+	/// Loads a session saved with `save`, remembering at most `cap` words.
+	///
+	/// # Behavior
+	/// - A missing file gives an empty session, so that the same path can
+	///   be used for the first run and the following ones.
+	/// - Empty lines are ignored; beyond `cap`, only the most recent words are kept.
+	///
+	/// # Errors
+	/// Returns an error if `cap` is `Some(0)` or if the file cannot be read.
+	pub fn load<P: AsRef<Path>>(path: P, cap: Option<usize>) -> Result<Self, Box<dyn std::error::Error>> {
+		let mut session = Self::new(cap)?;
+		if path.as_ref().exists() {
+			for line in read_file(path)? {
+				if !line.is_empty() {
+					session.insert(&line);
+				}
+			}
+		}
+		Ok(session)
+	}
+
+	/// Saves the remembered words to a text file, one per line, oldest first.
+	///
+	/// # Errors
+	/// Returns an error if the file cannot be written.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+		let mut contents = String::new();
+		for word in &self.words {
+			contents.push_str(word);
+			contents.push('\n');
+		}
+		fs::write(path, contents)?;
+		Ok(())
+	}
+
+	/// Returns the maximum number of remembered words, if any.
+	pub fn cap(&self) -> Option<usize> {
+		self.cap
+	}
+
+	/// Sets the maximum number of remembered words (`None` = unbounded).
+	///
+	/// The oldest words are forgotten if the session holds more.
+	///
+	/// # Errors
+	/// Returns an error if `cap` is `Some(0)`.
+	pub fn set_cap(&mut self, cap: Option<usize>) -> Result<(), String> {
+		if cap == Some(0) {
+			return Err("Session cap must be at least 1".to_owned());
+		}
+		self.cap = cap;
+		self.truncate();
+		Ok(())
+	}
+
+	/// Returns the number of remembered words.
+	pub fn len(&self) -> usize {
+		self.words.len()
+	}
+
+	/// Returns `true` if no word is remembered.
+	pub fn is_empty(&self) -> bool {
+		self.words.is_empty()
+	}
+
+	/// Returns the remembered words, oldest first.
+	pub fn words(&self) -> impl Iterator<Item = &str> {
+		self.words.iter().map(String::as_str)
+	}
+
+	/// Returns `true` if the word was produced in this session (case-insensitive).
+	pub fn contains(&self, word: &str) -> bool {
+		self.keys.contains(&word.to_lowercase())
+	}
+
+	/// Remembers a word; words already remembered are ignored.
+	///
+	/// Beyond the cap, the oldest word is forgotten.
+	pub fn insert(&mut self, word: &str) {
+		if self.keys.insert(word.to_lowercase()) {
+			self.words.push_back(word.to_owned());
+			self.truncate();
+		}
+	}
+
+	/// Forgets every word.
+	pub fn clear(&mut self) {
+		self.words.clear();
+		self.keys.clear();
+	}
+
+	/// Forgets the oldest words beyond the cap.
+	fn truncate(&mut self) {
+		let Some(cap) = self.cap else { return };
+		while self.words.len() > cap {
+			if let Some(oldest) = self.words.pop_front() {
+				self.keys.remove(&oldest.to_lowercase());
+			}
+		}
+	}
+}
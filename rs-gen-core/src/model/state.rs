@@ -1,6 +1,8 @@
+use super::distribution::Distribution;
+use super::prediction_input::Sampling;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Represents a state in an n-gram model.
 ///
@@ -12,12 +14,14 @@ use std::collections::HashMap;
 ///
 /// # Responsibilities
 /// - Accumulate transition counts during learning
+/// - Freeze transitions into a cumulative array for fast sampling
 /// - Predict the next character using weighted random sampling
 /// - Merge with another state having the same key (useful for parallel or incremental learning)
 ///
 /// # Invariants
 /// - All transitions belong to the same `key`
 /// - Each transition occurrence count is strictly positive
+/// - `cumulative` is either empty or matches `transitions`
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct State {
 	/// Identifier of the state (n-1 character prefix)
@@ -25,8 +29,17 @@ pub struct State {
 
 	/// Outgoing transitions indexed by the next character.
 	/// The value represents how many times this transition was observed.
-	/// Example: `{ 'e' => 42, 'a' => 3 }`
-	transitions: HashMap<char, usize>,
+	/// Example: `{ 'a' => 3, 'e' => 42 }`
+	///
+	/// Ordered by character so that sampling walks the transitions in the
+	/// same order on every run (required for seeded reproducibility).
+	transitions: BTreeMap<char, usize>,
+
+	/// Sampling-ready view of `transitions`: `(next_char, cumulative count)`
+	/// in character order. Built by `freeze`, cleared on any modification.
+	/// Not serialized; rebuilt after loading.
+	#[serde(skip)]
+	cumulative: Vec<(char, usize)>,
 }
 
 impl State {
@@ -34,7 +47,8 @@ impl State {
 	pub fn new(key: &str) -> Self {
 		Self {
 			key: key.to_owned(),
-			transitions: HashMap::new(),
+			transitions: BTreeMap::new(),
+			cumulative: Vec::new(),
 		}
 	}
 
@@ -44,23 +58,59 @@ impl State {
 	/// Otherwise, a new transition is created with an initial count of 1.
 	pub fn add_transition(&mut self, next_char: char) {
 		*self.transitions.entry(next_char).or_insert(0) += 1;
+		self.cumulative.clear();
+	}
+
+	/// Builds the cumulative array used for fast sampling.
+	///
+	/// Must be called again after the state is modified; until then,
+	/// `predict` falls back to a linear scan.
+	pub fn freeze(&mut self) {
+		let mut total = 0;
+		self.cumulative = self
+			.transitions
+			.iter()
+			.map(|(next_char, occurrence)| {
+				total += occurrence;
+				(*next_char, total)
+			})
+			.collect();
 	}
 
 	/// Predicts the next character using weighted random sampling.
 	///
 	/// The probability of selecting a character is proportional to its
-	/// occurrence count.
+	/// occurrence count, reshaped by `sampling`:
+	/// - `min_count`: transitions observed fewer times are ignored
+	/// - `temperature`: counts are raised to `1 / temperature`
+	/// - `top_k`: only the `k` most frequent transitions are kept
+	/// - `top_p`: only the most frequent transitions covering `p` of the mass are kept
 	///
 	/// # Algorithm
-	/// - Linear scan over all transitions (O(n) in the number of transitions)
-	/// - Subtracts randomly generated number from cumulative counts to select a bucket
+	/// - Frozen state: binary search over the cumulative array (O(log n))
+	/// - Otherwise: linear scan over all transitions (O(n)), subtracting a
+	///   randomly generated number from counts to select a bucket
+	/// - Reshaping `sampling`: the distribution is rebuilt for this call
+	/// - Both paths visit transitions in character order and select the same
+	///   bucket, so a given `rng` state always yields the same character
 	///
-	/// Returns `None` if the state has no transitions.
-	pub fn predict(&self) -> Option<char> {
+	/// Returns `None` if the state has no transitions, or if every transition
+	/// was filtered out by `sampling`.
+	pub fn predict<R: Rng + ?Sized>(&self, sampling: &Sampling, rng: &mut R) -> Option<char> {
 		if self.transitions.is_empty() {
 			return None;
 		}
 
+		if !sampling.is_identity() {
+			return self.distribution(sampling).sample(rng);
+		}
+
+		if let Some((_, total)) = self.cumulative.last() {
+			let r = rng.random_range(0..*total);
+			let index = self.cumulative.partition_point(|(_, cumulative)| *cumulative <= r);
+			return self.cumulative.get(index).map(|(next_char, _)| *next_char);
+		}
+
 		// Compute the total number of occurrences
 		let total: usize = self.transitions.values().sum();
 		if total == 0 {
@@ -69,7 +119,7 @@ impl State {
 		}
 
 		// Randomly select a character
-		let mut r = rand::rng().random_range(0..total);
+		let mut r = rng.random_range(0..total);
 		let mut fallback: Option<char> = None;
 
 		for (next_char, occurrence) in &self.transitions {
@@ -84,6 +134,29 @@ impl State {
 		fallback
 	}
 
+	/// Returns this state with characters replaced in its key and transitions.
+	///
+	/// `map` must be injective on the transition characters. The state is left unfrozen.
+	pub(crate) fn replace_chars(self, map: impl Fn(char) -> char) -> Self {
+		Self {
+			key: self.key.chars().map(&map).collect(),
+			transitions: self.transitions.into_iter().map(|(c, occurrence)| (map(c), occurrence)).collect(),
+			cumulative: Vec::new(),
+		}
+	}
+
+	/// Returns the observed transitions and their occurrence counts, in character order.
+	pub(crate) fn transitions(&self) -> &BTreeMap<char, usize> {
+		&self.transitions
+	}
+
+	/// Returns the transitions of this state as a distribution reshaped by `sampling`.
+	pub(crate) fn distribution(&self, sampling: &Sampling) -> Distribution {
+		let mut distribution = Distribution::from_counts(&self.transitions, sampling.min_count());
+		distribution.shape(sampling);
+		distribution
+	}
+
 	/// Merges another state into this one.
 	///
 	/// Both states must represent the same prefix (`key`).
@@ -101,6 +174,7 @@ impl State {
 		for (next_char, occurrence) in &other.transitions {
 			*self.transitions.entry(*next_char).or_insert(0) += *occurrence;
 		}
+		self.cumulative.clear();
 
 		Ok(())
 	}
@@ -0,0 +1,168 @@
+/// Default vowel class (`V`), covering the accented vowels of the bundled corpora.
+const DEFAULT_VOWELS: &str = "aeiouyàâäéèêëîïôöùûüÿæœ";
+
+/// Default consonant class (`C`).
+const DEFAULT_CONSONANTS: &str = "bcçdfghjklmnñpqrstvwxz";
+
+/// Returns the characters of a built-in class, if `class` is one.
+///
+/// - `V`: vowels
+/// - `C`: consonants
+pub(crate) fn default_class(class: char) -> Option<&'static str> {
+	match class {
+		'V' => Some(DEFAULT_VOWELS),
+		'C' => Some(DEFAULT_CONSONANTS),
+		_ => None,
+	}
+}
+
+/// A single position of a template.
+#[derive(Clone, Debug, PartialEq)]
+enum Element {
+	/// Any character of the named class (uppercase ASCII letter).
+	Class(char),
+	/// Exactly this character.
+	Literal(char),
+}
+
+/// Consonant/vowel skeleton that generated words must follow.
+///
+/// # Syntax
+/// - Uppercase ASCII letter: any character of that class (`C` and `V` are
+///   built in; other classes can be defined per model)
+/// - `?`: the preceding element is optional
+/// - `\x`: the literal character `x`
+/// - Any other character: that literal character (lowercased)
+///
+/// Example: `CV?CVn` is a consonant, an optional vowel, a consonant,
+/// a vowel, then `n`.
+///
+/// # Invariants
+/// - `elements` and `optional` have the same length
+/// - `?` never appears first
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+	elements: Vec<Element>,
+	optional: Vec<bool>,
+	source: String,
+}
+
+impl Template {
+	/// Parses a template.
+	///
+	/// # Errors
+	/// Returns an error if the template is empty, starts with `?`,
+	/// repeats `?` or ends with a lone `\`.
+	pub fn parse(template: &str) -> Result<Self, String> {
+		let mut elements = Vec::new();
+		let mut optional: Vec<bool> = Vec::new();
+		let mut chars = template.chars();
+
+		while let Some(c) = chars.next() {
+			match c {
+				'?' => match optional.last_mut() {
+					Some(last) if !*last => *last = true,
+					Some(_) => return Err(format!("Invalid template '{}': repeated '?'", template)),
+					None => return Err(format!("Invalid template '{}': '?' must follow an element", template)),
+				},
+				'\\' => match chars.next() {
+					Some(literal) => {
+						elements.extend(literal.to_lowercase().map(Element::Literal));
+						optional.resize(elements.len(), false);
+					}
+					None => return Err(format!("Invalid template '{}': trailing '\\'", template)),
+				},
+				c if c.is_ascii_uppercase() => {
+					elements.push(Element::Class(c));
+					optional.push(false);
+				}
+				c => {
+					elements.extend(c.to_lowercase().map(Element::Literal));
+					optional.resize(elements.len(), false);
+				}
+			}
+		}
+
+		if elements.is_empty() {
+			return Err("Template cannot be empty".to_owned());
+		}
+		Ok(Self { elements, optional, source: template.to_owned() })
+	}
+
+	/// Returns the template as written.
+	pub fn as_str(&self) -> &str {
+		&self.source
+	}
+
+	/// Returns the classes used by the template, without duplicates.
+	pub(crate) fn classes(&self) -> Vec<char> {
+		let mut classes: Vec<char> = self
+			.elements
+			.iter()
+			.filter_map(|element| match element {
+				Element::Class(class) => Some(*class),
+				Element::Literal(_) => None,
+			})
+			.collect();
+		classes.sort_unstable();
+		classes.dedup();
+		classes
+	}
+
+	/// Returns `true` if the element at `position` accepts `c`.
+	fn accepts<F: Fn(char, char) -> bool>(&self, position: usize, c: char, in_class: &F) -> bool {
+		match self.elements[position] {
+			Element::Class(class) => in_class(class, c),
+			Element::Literal(literal) => literal == c,
+		}
+	}
+
+	/// Adds every position reachable by skipping optional elements.
+	fn close(&self, positions: &mut [bool]) {
+		for position in 0..self.elements.len() {
+			if positions[position] && self.optional[position] {
+				positions[position + 1] = true;
+			}
+		}
+	}
+
+	/// Returns the template positions reachable after reading `word`.
+	///
+	/// Position `i` means the next character must match element `i`;
+	/// position `len` means the template is complete.
+	/// `in_class(class, c)` tells whether `c` belongs to `class`.
+	/// The result is all `false` if `word` does not follow the template.
+	pub(crate) fn positions<F: Fn(char, char) -> bool>(&self, word: &str, in_class: &F) -> Vec<bool> {
+		let mut positions = vec![false; self.elements.len() + 1];
+		positions[0] = true;
+		self.close(&mut positions);
+
+		for c in word.chars().flat_map(char::to_lowercase) {
+			let mut next = vec![false; self.elements.len() + 1];
+			for position in 0..self.elements.len() {
+				if positions[position] && self.accepts(position, c, in_class) {
+					next[position + 1] = true;
+				}
+			}
+			self.close(&mut next);
+			positions = next;
+		}
+		positions
+	}
+
+	/// Returns `true` if `c` can follow a word whose reachable positions are `positions`.
+	pub(crate) fn allows<F: Fn(char, char) -> bool>(&self, positions: &[bool], c: char, in_class: &F) -> bool {
+		let c = c.to_lowercase().next().unwrap_or(c);
+		(0..self.elements.len()).any(|position| positions[position] && self.accepts(position, c, in_class))
+	}
+
+	/// Returns `true` if the template can end at one of `positions`.
+	pub(crate) fn is_complete(&self, positions: &[bool]) -> bool {
+		positions[self.elements.len()]
+	}
+
+	/// Returns `true` if `word` follows the whole template.
+	pub(crate) fn matches<F: Fn(char, char) -> bool>(&self, word: &str, in_class: &F) -> bool {
+		self.is_complete(&self.positions(word, in_class))
+	}
+}
@@ -0,0 +1,197 @@
+use super::multigram_model::{END_CHAR, MultiGramModel, START_CHAR};
+use super::prediction_input::Sampling;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Code standing for any rare token (see `RARE_COUNT`).
+const RARE_TOKEN: char = '\u{F0000}';
+
+/// Tokens observed at most this many times in a model are rare: they are
+/// learned as `RARE_TOKEN`, so that their context is shared.
+const RARE_COUNT: usize = 1;
+
+/// Private-use planes holding the token codes, after `RARE_TOKEN`.
+const CODE_RANGES: [(u32, u32); 2] = [(0xF0001, 0xFFFFD), (0x100000, 0x10FFFD)];
+
+/// Splits a sentence into lowercase whitespace-separated tokens.
+pub(crate) fn tokenize(sentence: &str) -> impl Iterator<Item = String> + '_ {
+	sentence.split_whitespace().map(str::to_lowercase)
+}
+
+/// Counts the tokens of a set of sentences.
+fn count_tokens<'a>(sentences: impl Iterator<Item = &'a String>) -> BTreeMap<String, usize> {
+	let mut counts = BTreeMap::new();
+	for sentence in sentences {
+		for token in tokenize(sentence) {
+			*counts.entry(token).or_insert(0) += 1;
+		}
+	}
+	counts
+}
+
+/// Bidirectional mapping between tokens and the characters encoding them.
+///
+/// Token models reuse the character n-gram machinery: each token is encoded
+/// as one private-use character, so a sentence becomes a string with one
+/// character per token. The vocabulary is shared by every model of a
+/// generator, so that token models can be mixed.
+///
+/// # Invariants
+/// - `tokens[i]` is encoded by the `i`-th code of `CODE_RANGES`
+/// - `codes` is the inverse of `tokens`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Vocabulary {
+	codes: BTreeMap<String, char>,
+	tokens: Vec<String>,
+}
+
+impl Vocabulary {
+	/// Builds the vocabulary of the tokens that are not rare in at least one model.
+	///
+	/// # Errors
+	/// Returns an error if there are more tokens than available codes.
+	pub(crate) fn build<'a>(models: impl Iterator<Item = &'a MultiGramModel>) -> Result<Self, String> {
+		let mut vocabulary = Self::default();
+		for model in models {
+			for (token, count) in count_tokens(model.sentences()) {
+				if count > RARE_COUNT && !vocabulary.codes.contains_key(&token) {
+					let code = Self::code(vocabulary.tokens.len())
+						.ok_or_else(|| "Too many distinct tokens to encode".to_owned())?;
+					vocabulary.codes.insert(token.clone(), code);
+					vocabulary.tokens.push(token);
+				}
+			}
+		}
+		Ok(vocabulary)
+	}
+
+	/// Returns the `index`-th code, if there are enough.
+	fn code(index: usize) -> Option<char> {
+		let mut index = index as u32;
+		for (first, last) in CODE_RANGES {
+			if index <= last - first {
+				return char::from_u32(first + index);
+			}
+			index -= last - first + 1;
+		}
+		None
+	}
+
+	/// Returns the code of a (lowercase) token, or `RARE_TOKEN` if it is not in the vocabulary.
+	pub(crate) fn encode(&self, token: &str) -> char {
+		self.codes.get(token).copied().unwrap_or(RARE_TOKEN)
+	}
+
+	/// Returns the token encoded by `code`, if any.
+	pub(crate) fn decode(&self, code: char) -> Option<&str> {
+		let code = code as u32;
+		let mut offset = 0;
+		for (first, last) in CODE_RANGES {
+			if (first..=last).contains(&code) {
+				return self.tokens.get((offset + code - first) as usize).map(String::as_str);
+			}
+			offset += last - first + 1;
+		}
+		None
+	}
+}
+
+/// Word-token view of a `MultiGramModel`.
+///
+/// # Responsibilities
+/// - Learn transitions between tokens, encoded with a shared `Vocabulary`
+/// - Keep the rare tokens of the corpus, replacing `RARE_TOKEN` on output
+/// - Spell novel tokens with a character model trained on the rare tokens
+#[derive(Clone, Debug)]
+pub(crate) struct TokenModel {
+	/// Model over encoded sentences (one character per token).
+	model: MultiGramModel,
+
+	/// Distinct rare tokens, sorted.
+	rare: Vec<String>,
+
+	/// Character model trained on the rare tokens.
+	spelling: MultiGramModel,
+}
+
+impl TokenModel {
+	/// Trains token models on `sentences`.
+	///
+	/// Tokens that are rare in these sentences are learned as `RARE_TOKEN`,
+	/// even if they are in the vocabulary because of another model.
+	pub(crate) fn train<'a>(
+		sentences: impl Iterator<Item = &'a String> + Clone,
+		vocabulary: &Vocabulary,
+		name: &str,
+	) -> Result<Self, String> {
+		let counts = count_tokens(sentences.clone());
+		let rare: Vec<String> = counts
+			.iter()
+			.filter(|(_, count)| **count <= RARE_COUNT)
+			.map(|(token, _)| token.clone())
+			.collect();
+
+		let encoded: Vec<String> = sentences
+			.map(|sentence| {
+				tokenize(sentence)
+					.map(|token| if counts[&token] > RARE_COUNT { vocabulary.encode(&token) } else { RARE_TOKEN })
+					.collect()
+			})
+			.collect();
+
+		let mut model = MultiGramModel::from_lines(&encoded)?;
+		model.set_name(name);
+		model.freeze();
+
+		let mut spelling = MultiGramModel::from_lines(&rare)?;
+		spelling.set_name(name);
+		spelling.freeze();
+
+		Ok(Self { model, rare, spelling })
+	}
+
+	/// Returns the model over encoded sentences.
+	pub(crate) fn model(&self) -> &MultiGramModel {
+		&self.model
+	}
+
+	/// Returns a token to output in place of `RARE_TOKEN`.
+	///
+	/// With probability `novel_tokens`, a new token is spelled character by
+	/// character; otherwise a rare token of the corpus is picked uniformly.
+	/// Returns `None` if the model has no rare token.
+	pub(crate) fn rare_token<R: Rng + ?Sized>(
+		&self,
+		novel_tokens: f32,
+		max_n: usize,
+		sampling: &Sampling,
+		rng: &mut R,
+	) -> Option<String> {
+		if self.rare.is_empty() {
+			return None;
+		}
+		if novel_tokens > 0.0 && rng.random_range(0.0..1.0) < novel_tokens {
+			let token = self.spell(max_n, sampling, rng);
+			if !token.is_empty() {
+				return Some(token);
+			}
+		}
+		Some(self.rare[rng.random_range(0..self.rare.len())].clone())
+	}
+
+	/// Spells a token with the character model, backing off on missing keys.
+	fn spell<R: Rng + ?Sized>(&self, max_n: usize, sampling: &Sampling, rng: &mut R) -> String {
+		let mut key = String::from(START_CHAR);
+		loop {
+			let key_len = key.chars().count();
+			let top = if max_n < 2 { key_len + 1 } else { max_n.min(key_len + 1) };
+			let next = (2..=top).rev().find_map(|n| self.spelling.predict(&key, n, sampling, rng).ok().flatten());
+			match next {
+				Some(c) if c != END_CHAR => key.push(c),
+				_ => break,
+			}
+		}
+		key.remove(0);
+		key
+	}
+}
@@ -0,0 +1,32 @@
+/// Returns the Levenshtein distance between two strings, counted in characters.
+///
+/// Returns `None` as soon as the distance is known to exceed `limit`,
+/// which makes nearest-neighbour searches much cheaper.
+pub(crate) fn edit_distance(a: &str, b: &str, limit: usize) -> Option<usize> {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+	edit_distance_chars(&a, &b, limit)
+}
+
+/// Same as `edit_distance`, on words already split into characters.
+pub(crate) fn edit_distance_chars(a: &[char], b: &[char], limit: usize) -> Option<usize> {
+	if a.len().abs_diff(b.len()) > limit {
+		return None;
+	}
+
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	let mut current = vec![0; b.len() + 1];
+	for (i, a_char) in a.iter().enumerate() {
+		current[0] = i + 1;
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(a_char != b_char);
+			current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+		}
+		if current.iter().min().is_some_and(|best| *best > limit) {
+			return None;
+		}
+		std::mem::swap(&mut previous, &mut current);
+	}
+
+	Some(previous[b.len()]).filter(|distance| *distance <= limit)
+}
@@ -1,17 +1,37 @@
+use rs_gen_core::model::evaluation::Evaluation;
 use rs_gen_core::model::generator::Generator;
-use rs_gen_core::model::prediction_input::StartSeed;
+use rs_gen_core::model::prediction_input::{Blending, Casing, EndSeed, Smoothing, StartSeed, Tokenization};
+use rs_gen_core::model::session::GenerationSession;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load all models from the "data" directory (.dat files)
     // Load automatically .bin is existing
+    // Equivalent spellings can be merged at training time (NFC/NFD/NFKC,
+    // accent folding, allowed alphabet, case sensitivity); each setting has its own cache
+    // e.g. let mut normalization = Normalization::default();
+    //      normalization.set_form(NormalizationForm::Nfc);
+    //      Generator::with_normalization("./data", normalization)?;
     let mut app: Generator = Generator::new("./data")?;
 
+    // Character classes used by templates can be defined per model
+    // 'C' (consonants) and 'V' (vowels) are built in
+    app.set_character_class("pokemon", 'L', "lr")?;
+
     // Create a prediction input with default intensity values for all models
     let mut input = app.make_prediction_input();
 
     // Number of retries if the generated word is already in the learning database
     input.nb_try = 100;
 
+    // How close to a training word counts as a duplicate
+    // By default only exact copies (ignoring case) are rejected
+    // 'distance' also rejects words within that many edits ("pikachi" for "pikachu")
+    input.novelty.set_fold_accents(true);
+    input.novelty.set_distance(1);
+    // Exact copies can also be avoided while sampling, without retrying:
+    // the end of the word is masked whenever it would complete a training word
+    input.novelty.set_during_generation(true);
+
     // Maximum n-gram size to consider during generation
     input.max_n = 0;
 
@@ -21,11 +41,51 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 'Custom' uses a custom string as the seed
     input.start_seed = StartSeed::False;
 
+    // End seed generates words backward from a fixed ending
+    // It requires the reversed models: app.train_reversed()?;
+    // e.g. input.end_seed = EndSeed::Custom(" sur mer".to_owned());
+    input.end_seed = EndSeed::False;
+
+    // Seed of the random number generator
+    // 'None' draws a new seed on every call
+    // 'Some(seed)' makes generation reproducible (same seed, same word)
+    input.rng_seed = None;
+
     // If the current internal key is not found in the database,
     // it will be reduced by 1. If 'reduce_random' is true,
     // the reduction can be random (results will be very random)
     input.reduce_random = false;
 
+    // Adaptive order lowers n while the current state has a single continuation
+    // (or fewer observations than the threshold), so that long training
+    // substrings are not copied verbatim. 'None' disables it
+    input.set_adaptive_order(Some(3));
+
+    // How n-gram sizes are combined
+    // 'Backoff' uses a single n-gram size and reduces it when the key is missing
+    // 'WittenBell' mixes all available n-gram sizes into one distribution
+    input.smoothing = Smoothing::Backoff;
+
+    // How weighted models are combined for each character
+    // 'PerCharacter' picks one model per character according to the intensities
+    // 'Mixture' mixes the distributions of all models according to the intensities
+    input.blending = Blending::PerCharacter;
+
+    // Unit of the n-grams
+    // 'Characters' learns which character follows which
+    // 'Words' learns which token follows which, for multi-word corpora
+    // It requires the token models: app.train_tokens()?;
+    // 'Graphemes' learns user-perceived characters, so that combining accents
+    // and emoji sequences are never split
+    // It requires the grapheme models: app.train_graphemes()?;
+    input.tokenization = Tokenization::Characters;
+
+    // Casing of the generated words, applied to each part of multi-word names
+    // 'Unchanged' keeps them as generated (lowercase unless models are case-sensitive)
+    // 'Learned' uses the majority casing of the corpora ("MAROLLES", but "sur")
+    // 'Title', 'Upper' and 'Lower' force a casing
+    input.casing = Casing::Learned;
+
     // Set the intensity for each model (not normalized; will be computed in 'models_probability')
     input.set_model_intensity("french", 100.0)?;
     input.set_model_intensity("fromage", 50.0)?;
@@ -51,6 +111,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err(_) => println!("Randomness -1.0 is invalid, must be between 0.0 and 1.0"),
     }
 
+    // Set the sampling temperature (must be greater than 0.0)
+    // Below 1.0 output is closer to the corpus, above 1.0 it is more adventurous
+    input.set_temperature(0.8)?;
+
+    // Optional transition filters applied to each state before sampling
+    // 'min_count' drops transitions seen fewer times (e.g. one-off typos)
+    // 'top_k' keeps the k most likely transitions
+    // 'top_p' keeps the most likely transitions covering p of the probability mass
+    input.set_min_count(2);
+    input.set_top_k(None)?;
+    input.set_top_p(Some(0.95))?;
+
+    // Bound the length of generated words (in characters)
+    // 'end_scaling' makes short words unlikely to end early and pushes long ones to finish
+    input.set_length_bounds(4, Some(16))?;
+    input.set_end_scaling(2.0)?;
+
+    // Constraints on the generated words (case-insensitive)
+    // They are honored while sampling; generation fails if they cannot be met
+    // e.g. input.constraints.set_ends_with(Some("ville"))?;
+    input.constraints.add_excludes("zz")?;
+    input.constraints.set_regex(None)?;
+
+    // Optional consonant/vowel skeleton (uppercase letters are classes, '?' makes
+    // the previous element optional, other characters are literals)
+    // e.g. input.template = Some(Template::parse("CV?CVn")?);
+    input.template = None;
+
     // The probability of each model is computed based on its intensity
     // The sum of all probabilities should be 1.0
     for (name, prob) in input.models_probability() {
@@ -58,9 +146,46 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Generate 10 words using the input settings
+    // The session remembers them, so that none is produced twice (at most 1000 are kept)
+    // It can be kept across runs: session.save(path)? and GenerationSession::load(path, cap)?
+    let mut session = GenerationSession::new(Some(1000))?;
     for i in 0..10 {
-        println!("Generated word {}: {}", i + 1, app.predict(&input)?);
+        println!("Generated word {}: {}", i + 1, app.predict_in_session(&input, &mut session)?);
+    }
+
+    // Generate 20 distinct words at once, spread over all CPU cores
+    // With a pairwise distance, they are also more than that many edits apart
+    input.novelty.set_pairwise_distance(1);
+    println!("Batch: {}", app.predict_many(&input, 20, true)?.join(", "));
+
+    // Find the 5 most probable new words (beam search, beam width 50)
+    for (word, log_prob) in app.best_words(&input, 5, 50)? {
+        println!("Best word: {} (log-probability {:.2})", word, log_prob);
     }
 
+    // Perplexity of a word under each model (lower means more typical)
+    for (name, score) in app.score("camembert", &input) {
+        println!("camembert under {}: perplexity {:.2}", name, score.perplexity());
+    }
+
+    // Which corpus does a word most likely come from?
+    for (name, probability) in app.classify("Roquefortville") {
+        println!("Roquefortville is {}: {:.2}", name, probability);
+    }
+
+    // Evaluate settings on a corpus: 90% for training, 10% held out
+    let evaluation = Evaluation::new("./data/fromage.dat", 0.9, 42)?;
+    let mut eval_input = evaluation.make_prediction_input();
+    eval_input.max_n = 4;
+    eval_input.rng_seed = Some(42);
+    let report = evaluation.run(&eval_input, 100)?;
+    println!(
+        "Held-out perplexity {:.2}, novelty {:.0}%, distinct-2 {:.2}, nearest training word at {:.2} edits",
+        report.perplexity,
+        report.novelty_rate * 100.0,
+        report.distinct.get(&2).copied().unwrap_or(0.0),
+        report.mean_nearest_distance
+    );
+
     Ok(())
 }
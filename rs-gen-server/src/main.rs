@@ -4,7 +4,9 @@ use actix_web::{get, web, App, HttpResponse, HttpServer, Responder, http};
 use actix_cors::Cors;
 
 use rs_gen_core::model::generator::Generator;
-use rs_gen_core::model::prediction_input::StartSeed;
+use rs_gen_core::model::prediction_input::{Blending, Casing, EndSeed, Smoothing, StartSeed, Tokenization};
+use rs_gen_core::model::session::GenerationSession;
+use rs_gen_core::model::template::Template;
 use serde::Deserialize;
 
 /// Query parameters for the `/v1/generate` endpoint
@@ -18,16 +20,94 @@ struct GenerateParams {
 	randomness: Option<f32>,
 	/// Whether to reduce randomness progressively
 	reduce_random: Option<bool>,
+	/// Lower n while its state has one transition or fewer observations than this (optional; default disabled)
+	adaptive_order: Option<usize>,
+	/// Sampling temperature (> 0.0); below 1.0 is conservative, above is adventurous
+	temperature: Option<f32>,
+	/// Keep only the k most likely transitions of each state (optional)
+	top_k: Option<usize>,
+	/// Keep only the most likely transitions covering p of the mass, in (0.0, 1.0] (optional)
+	top_p: Option<f32>,
+	/// Ignore transitions observed fewer than min_count times (optional; default 0 = disabled)
+	min_count: Option<usize>,
+	/// How n-gram orders are combined: "backoff" (default) or "witten_bell"
+	smoothing: Option<String>,
+	/// How weighted models are combined: "per_character" (default) or "mixture"
+	blending: Option<String>,
 	/// Seed string controlling the starting prefix
 	/// Formats: "none", "custom:<string>", "random:<n-gram>"
 	seed: Option<String>,
+	/// Seed string fixing the ending; the word is generated backward,
+	/// or between both seeds when `seed` is also set
+	/// Formats: "none", "custom:<string>", "random:<n-gram>"
+	end_seed: Option<String>,
 	/// Per-model intensity weights, format: "name1:0.5,name2:0.25"
 	intensity: Option<String>,
+	/// Minimum word length in characters (optional; default 0 = no minimum)
+	min_len: Option<usize>,
+	/// Maximum word length in characters (optional; default unbounded)
+	max_len: Option<usize>,
+	/// Strength of the length-aware end scaling (optional; default 0.0 = disabled)
+	end_scaling: Option<f32>,
+	/// Required suffix (optional)
+	ends_with: Option<String>,
+	/// Comma-separated substrings that must appear (optional)
+	contains: Option<String>,
+	/// Comma-separated substrings that must not appear (optional)
+	excludes: Option<String>,
+	/// Regular expression the word must match, case-insensitive (optional)
+	regex: Option<String>,
+	/// Consonant/vowel skeleton, e.g. "CVCCV" or "CV?CVn" (optional)
+	template: Option<String>,
+	/// Seed of the random number generator (optional; same seed, same word)
+	rng_seed: Option<u64>,
+	/// Unit of the n-grams: "characters" (default), "words" or "graphemes"
+	tokenization: Option<String>,
+	/// With "words", probability of spelling a new token where a rare one is expected (default 0.0)
+	novel_tokens: Option<f32>,
+	/// Casing of the word: "unchanged" (default), "learned", "title", "upper" or "lower"
+	casing: Option<String>,
+	/// Reject words within this many edits of a training word (optional; default 0 = exact copies only)
+	novelty_distance: Option<usize>,
+	/// Ignore accents when comparing words with training words (optional; default false)
+	novelty_fold_accents: Option<bool>,
+	/// Mask the end of the word while it would complete a training word (optional; default false)
+	novelty_during_generation: Option<bool>,
+	/// Reject words of a batch within this many edits of each other (optional; default 0 = identical words only)
+	pairwise_distance: Option<usize>,
+	/// Never return a word already returned with `unique=true` (optional; default false)
+	unique: Option<bool>,
+	/// Number of distinct words to generate, one per line (optional; default 1, at most `MAX_COUNT`)
+	count: Option<usize>,
+}
+
+/// Query parameters for the `/v1/classify` endpoint
+#[derive(Deserialize)]
+struct ClassifyParams {
+	/// Word to classify
+	word: String,
 }
 
+/// Number of words remembered by the server session (see `GenerateParams::unique`)
+const SESSION_CAP: usize = 10_000;
+
+/// Environment variable naming the file the server session is kept in
+const SESSION_PATH_VAR: &str = "RS_GEN_SESSION";
+
+/// Session file used when `SESSION_PATH_VAR` is not set (outside `./data`,
+/// whose files are all loaded as models)
+const DEFAULT_SESSION_PATH: &str = "./session.txt";
+
+/// Maximum number of words generated by one request (see `GenerateParams::count`)
+const MAX_COUNT: usize = 1_000;
+
 /// Shared application state for Actix, wrapping the generator in a Mutex
 struct SharedData {
 	model: Generator,
+	/// Words already returned with `unique=true`
+	session: GenerationSession,
+	/// File the session is saved to after each update
+	session_path: String,
 }
 
 impl GenerateParams {
@@ -41,7 +121,26 @@ impl GenerateParams {
 	/// # Errors
 	/// Returns a `String` describing invalid formats or values
 	fn start_seed(&self) -> Result<StartSeed, String> {
-		let seed : String = match &self.seed  {
+		Self::parse_seed(self.seed.as_deref())
+	}
+
+	/// Computes the ending seed strategy for backward generation
+	///
+	/// Same formats as `start_seed`.
+	///
+	/// # Errors
+	/// Returns a `String` describing invalid formats or values
+	fn end_seed(&self) -> Result<EndSeed, String> {
+		Ok(match Self::parse_seed(self.end_seed.as_deref())? {
+			StartSeed::False => EndSeed::False,
+			StartSeed::Custom(s) => EndSeed::Custom(s),
+			StartSeed::Random(n) => EndSeed::Random(n),
+		})
+	}
+
+	/// Parses a seed string: "none", "custom:<s>" or "random:<n>"
+	fn parse_seed(seed: Option<&str>) -> Result<StartSeed, String> {
+		let seed : String = match seed {
 			None => return Ok(StartSeed::False),
 			Some(s) => s.to_lowercase()
 		};
@@ -71,6 +170,79 @@ impl GenerateParams {
 			_ => Err("Seed must start with 'custom:', 'random:', or be 'none'".into()),
 		}
 	}
+
+	/// Computes the smoothing method used to combine n-gram orders
+	///
+	/// # Returns
+	/// - `Smoothing::Backoff` if not specified or "backoff"
+	/// - `Smoothing::WittenBell` if "witten_bell"
+	///
+	/// # Errors
+	/// Returns a `String` describing an unknown method
+	fn smoothing(&self) -> Result<Smoothing, String> {
+		match self.smoothing.as_deref().map(str::to_lowercase).as_deref() {
+			None | Some("backoff") => Ok(Smoothing::Backoff),
+			Some("witten_bell") => Ok(Smoothing::WittenBell),
+			Some(s) => Err(format!("Unknown smoothing '{}', expected 'backoff' or 'witten_bell'", s)),
+		}
+	}
+
+	/// Computes the casing of the word
+	///
+	/// # Returns
+	/// - `Casing::Unchanged` if not specified or "unchanged"
+	/// - `Casing::Learned`, `Casing::Title`, `Casing::Upper` or `Casing::Lower`
+	///   if "learned", "title", "upper" or "lower"
+	///
+	/// # Errors
+	/// Returns a `String` describing an unknown casing
+	fn casing(&self) -> Result<Casing, String> {
+		match self.casing.as_deref().map(str::to_lowercase).as_deref() {
+			None | Some("unchanged") => Ok(Casing::Unchanged),
+			Some("learned") => Ok(Casing::Learned),
+			Some("title") => Ok(Casing::Title),
+			Some("upper") => Ok(Casing::Upper),
+			Some("lower") => Ok(Casing::Lower),
+			Some(s) => Err(format!(
+				"Unknown casing '{}', expected 'unchanged', 'learned', 'title', 'upper' or 'lower'",
+				s
+			)),
+		}
+	}
+
+	/// Computes the unit of the n-grams
+	///
+	/// # Returns
+	/// - `Tokenization::Characters` if not specified or "characters"
+	/// - `Tokenization::Words` if "words"
+	/// - `Tokenization::Graphemes` if "graphemes"
+	///
+	/// # Errors
+	/// Returns a `String` describing an unknown unit
+	fn tokenization(&self) -> Result<Tokenization, String> {
+		match self.tokenization.as_deref().map(str::to_lowercase).as_deref() {
+			None | Some("characters") => Ok(Tokenization::Characters),
+			Some("words") => Ok(Tokenization::Words),
+			Some("graphemes") => Ok(Tokenization::Graphemes),
+			Some(s) => Err(format!("Unknown tokenization '{}', expected 'characters', 'words' or 'graphemes'", s)),
+		}
+	}
+
+	/// Computes how weighted models are combined for each character
+	///
+	/// # Returns
+	/// - `Blending::PerCharacter` if not specified or "per_character"
+	/// - `Blending::Mixture` if "mixture"
+	///
+	/// # Errors
+	/// Returns a `String` describing an unknown mode
+	fn blending(&self) -> Result<Blending, String> {
+		match self.blending.as_deref().map(str::to_lowercase).as_deref() {
+			None | Some("per_character") => Ok(Blending::PerCharacter),
+			Some("mixture") => Ok(Blending::Mixture),
+			Some(s) => Err(format!("Unknown blending '{}', expected 'per_character' or 'mixture'", s)),
+		}
+	}
 }
 
 /// HTTP GET `/v1/generate` endpoint
@@ -82,7 +254,7 @@ impl GenerateParams {
 /// - See `GenerateParams` struct
 ///
 /// # Returns
-/// - 200 OK with generated sequence
+/// - 200 OK with generated sequence (`count` distinct sequences, one per line)
 /// - 400 BadRequest if parameters are invalid
 /// - 500 InternalServerError if the model is unavailable or mutex lock fails
 #[get("/v1/generate")]
@@ -94,17 +266,48 @@ async fn get_generated(
 	let nb_try = query.nb_try.unwrap_or(5);
 	let randomness = query.randomness.unwrap_or(0.1);
 	let reduce_random = query.reduce_random.unwrap_or(false);
+	let temperature = query.temperature.unwrap_or(1.0);
 
 	let start_seed = match query.start_seed() {
 		Ok(s) => s,
 		Err(e) => return HttpResponse::BadRequest().body(e),
 	};
+	let end_seed = match query.end_seed() {
+		Ok(s) => s,
+		Err(e) => return HttpResponse::BadRequest().body(e),
+	};
+	let smoothing = match query.smoothing() {
+		Ok(s) => s,
+		Err(e) => return HttpResponse::BadRequest().body(e),
+	};
+	let blending = match query.blending() {
+		Ok(b) => b,
+		Err(e) => return HttpResponse::BadRequest().body(e),
+	};
+	let tokenization = match query.tokenization() {
+		Ok(t) => t,
+		Err(e) => return HttpResponse::BadRequest().body(e),
+	};
 
 	let mut shared_data = match data.lock() {
 		Ok(m) => m,
 		Err(_) => return HttpResponse::InternalServerError().body("Model lock failed"),
 	};
 
+	// Reversed models are trained on the first request needing them
+	let backward = end_seed != EndSeed::False && start_seed == StartSeed::False;
+	if (backward || matches!(end_seed, EndSeed::Random(_))) && let Err(e) = shared_data.model.train_reversed() {
+		return HttpResponse::InternalServerError().body(e);
+	}
+	// Token models are trained on the first request needing them
+	if tokenization == Tokenization::Words && let Err(e) = shared_data.model.train_tokens() {
+		return HttpResponse::InternalServerError().body(e);
+	}
+	// Grapheme models are trained on the first request needing them
+	if tokenization == Tokenization::Graphemes && let Err(e) = shared_data.model.train_graphemes() {
+		return HttpResponse::InternalServerError().body(e);
+	}
+
 	// Prepare prediction input
 	let mut input = shared_data.model.make_prediction_input();
 	input.max_n = max_n;
@@ -113,7 +316,64 @@ async fn get_generated(
 		return HttpResponse::BadRequest().body(e);
 	}
 	input.reduce_random = reduce_random;
+	input.set_adaptive_order(query.adaptive_order);
+	if let Err(e) = input.set_temperature(temperature) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	if let Err(e) = input.set_top_k(query.top_k) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	if let Err(e) = input.set_top_p(query.top_p) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	input.set_min_count(query.min_count.unwrap_or(0));
+	if let Err(e) = input.set_length_bounds(query.min_len.unwrap_or(0), query.max_len) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	if let Err(e) = input.set_end_scaling(query.end_scaling.unwrap_or(0.0)) {
+		return HttpResponse::BadRequest().body(e);
+	}
 	input.start_seed = start_seed;
+	input.end_seed = end_seed;
+	input.rng_seed = query.rng_seed;
+	input.smoothing = smoothing;
+	input.blending = blending;
+	input.tokenization = tokenization;
+	input.casing = match query.casing() {
+		Ok(c) => c,
+		Err(e) => return HttpResponse::BadRequest().body(e),
+	};
+	if let Err(e) = input.set_novel_tokens(query.novel_tokens.unwrap_or(0.0)) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	input.novelty.set_distance(query.novelty_distance.unwrap_or(0));
+	input.novelty.set_fold_accents(query.novelty_fold_accents.unwrap_or(false));
+	input.novelty.set_during_generation(query.novelty_during_generation.unwrap_or(false));
+	input.novelty.set_pairwise_distance(query.pairwise_distance.unwrap_or(0));
+
+	// Word constraints
+	if let Err(e) = input.constraints.set_ends_with(query.ends_with.as_deref()) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	for substring in query.contains.iter().flat_map(|s| s.split(',')) {
+		if let Err(e) = input.constraints.add_contains(substring) {
+			return HttpResponse::BadRequest().body(e);
+		}
+	}
+	for substring in query.excludes.iter().flat_map(|s| s.split(',')) {
+		if let Err(e) = input.constraints.add_excludes(substring) {
+			return HttpResponse::BadRequest().body(e);
+		}
+	}
+	if let Err(e) = input.constraints.set_regex(query.regex.as_deref()) {
+		return HttpResponse::BadRequest().body(e);
+	}
+	if let Some(template) = &query.template {
+		match Template::parse(template) {
+			Ok(t) => input.template = Some(t),
+			Err(e) => return HttpResponse::BadRequest().body(e),
+		}
+	}
 
 	// Parse intensity query like "name1:0.5,name2:0.25"
 	if let Some(intensity_str) = &query.intensity {
@@ -133,7 +393,25 @@ async fn get_generated(
 		}
 	}
 
-	match shared_data.model.predict(&input) {
+	let count = query.count.unwrap_or(1);
+	if count == 0 || count > MAX_COUNT {
+		return HttpResponse::BadRequest().body(format!("Count must be between 1 and {}", MAX_COUNT));
+	}
+	let shared_data = &mut *shared_data;
+	let unique = query.unique.unwrap_or(false);
+	let result = match (count, unique) {
+		(1, true) => shared_data.model.predict_in_session(&input, &mut shared_data.session),
+		(1, false) => shared_data.model.predict(&input),
+		(_, true) => shared_data
+			.model
+			.predict_many_in_session(&input, count, &mut shared_data.session)
+			.map(|words| words.join("\n")),
+		(_, false) => shared_data.model.predict_many(&input, count, true).map(|words| words.join("\n")),
+	};
+	if unique && result.is_ok() && let Err(e) = shared_data.session.save(&shared_data.session_path) {
+		return HttpResponse::InternalServerError().body(format!("Failed to save session: {}", e));
+	}
+	match result {
 		Ok(result) => HttpResponse::Ok().body(result),
 		Err(e) => HttpResponse::InternalServerError().body(e),
 	}
@@ -151,6 +429,28 @@ async fn get_models(data: web::Data<Mutex<SharedData>>) -> impl Responder {
 	HttpResponse::Ok().body(shared_data.model.get_model_names().join("\n"))
 }
 
+/// HTTP GET `/v1/classify` endpoint
+///
+/// Returns a newline-separated list of `name:probability` pairs telling
+/// which model the word most likely comes from, most probable first.
+#[get("/v1/classify")]
+async fn get_classified(
+	data: web::Data<Mutex<SharedData>>,
+	query: web::Query<ClassifyParams>,
+) -> impl Responder {
+	let shared_data = match data.lock() {
+		Ok(m) => m,
+		Err(_) => return HttpResponse::InternalServerError().body("Model lock failed"),
+	};
+	let lines: Vec<String> = shared_data
+		.model
+		.classify(&query.word)
+		.into_iter()
+		.map(|(name, probability)| format!("{}:{}", name, probability))
+		.collect();
+	HttpResponse::Ok().body(lines.join("\n"))
+}
+
 /// Main entry point for the Actix web server
 ///
 /// Loads the generator, wraps it in a mutex, and starts an HTTP server.
@@ -166,7 +466,12 @@ async fn main() -> std::io::Result<()> {
 		Err(e) => panic!("Failed to load model: {}", e),
 	};
 
-	let shared_data = SharedData { model: generator };
+	let session_path = std::env::var(SESSION_PATH_VAR).unwrap_or_else(|_| DEFAULT_SESSION_PATH.to_owned());
+	let session = match GenerationSession::load(&session_path, Some(SESSION_CAP)) {
+		Ok(s) => s,
+		Err(e) => panic!("Failed to load session '{}': {}", session_path, e),
+	};
+	let shared_data = SharedData { model: generator, session, session_path };
 	let shared_model = web::Data::new(Mutex::new(shared_data));
 
 	HttpServer::new(move || {
@@ -180,6 +485,7 @@ async fn main() -> std::io::Result<()> {
 			.app_data(shared_model.clone())
 			.service(get_generated)
 			.service(get_models)
+			.service(get_classified)
 	})
 		.bind(("127.0.0.1", 5000))?
 		.run()